@@ -0,0 +1,167 @@
+use array_tool::vec::Intersect;
+use clap::{CommandFactory, Parser};
+use git_utils_shared::{
+    cli::{without_short, GlobalArgs},
+    complete,
+    report::{self, Report},
+    GitError, Repo,
+};
+use pathdiff::diff_paths;
+use serde::Serialize;
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+/// Run git inside the embedded repository of the current directory
+// egit's own flags are only read before the git command, everything from there on is git's
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None, trailing_var_arg = true)]
+// no -v, that is git's --version
+#[clap(mut_arg("verbose", without_short))]
+struct Args {
+    #[clap(flatten)]
+    global: GlobalArgs,
+    /// args to pass to git
+    #[clap(parse(from_os_str), allow_hyphen_values = true)]
+    args: Vec<OsString>,
+}
+
+/// A git command run inside the embedded repository.
+#[derive(Serialize)]
+struct Run {
+    args: Vec<String>,
+    /// Embedded repository the command ran in.
+    embed: String,
+    exit_code: i32,
+    stdout: String,
+    stderr: String,
+    /// New head written to `.gitembed` when the command moved it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head: Option<String>,
+}
+
+impl Report for Run {
+    fn human(&self, out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(self.stdout.as_bytes())
+    }
+
+    /// git's output is forwarded unchanged, pass git's own porcelain flags for stable output.
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(self.stdout.as_bytes())
+    }
+}
+
+/// The clap definition of egit, for completions and man pages.
+pub fn command() -> clap::Command<'static> {
+    Args::command()
+}
+
+/// Arguments are passed to git, which completes them itself: leave them to the shell.
+pub fn complete_value(_path: &[&str], _arg: &str, _prefix: &str) -> Vec<String> {
+    Vec::new()
+}
+/// Run egit with the command line `args`, the first one being the program name.
+///
+/// Exits the process when the tool fails; the multicall `git-excess` binary calls this too.
+pub fn main<I, T>(args: I)
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    complete::intercept(&args, command, complete_value);
+    let args = Args::parse_from(args);
+
+    match run(&args) {
+        Ok(code) => std::process::exit(code),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            std::process::exit(err.exit_code());
+        }
+    }
+}
+
+/// Run git inside the embedded repository and return its exit code.
+fn run(args: &Args) -> Result<i32, GitError> {
+    let cwd = std::env::current_dir()?;
+
+    let embedded_repo = match cwd
+        .ancestors()
+        .find(|parent_folder| parent_folder.join(".egit").is_dir())
+    {
+        Some(parent_folder) => Repo::embedded(parent_folder),
+        None => {
+            eprintln!("{} is not an embedded directory", cwd.display());
+            std::process::exit(1);
+        }
+    };
+    args.global.init(&embedded_repo)?;
+    let format = args.global.format.unwrap_or_default();
+
+    // run from cwd so that relative paths in the arguments keep working
+    let mut git = embedded_repo.git();
+    git.current_dir(&cwd);
+    // git pages and colors its own output, pass the choices on
+    if args.global.no_pager {
+        git.arg("--no-pager");
+    }
+    if let Some(color) = args.global.color {
+        git.arg("-c").arg(format!("color.ui={color}"));
+    }
+    git.args(&args.args);
+    let (code, stdout, stderr) = if format.is_human() {
+        let status = embedded_repo.runner().status(&mut git)?;
+        (status.code().unwrap_or(1), Vec::new(), Vec::new())
+    } else {
+        let output = embedded_repo.runner().output(&mut git)?;
+        (
+            output.status.code().unwrap_or(1),
+            output.stdout,
+            output.stderr,
+        )
+    };
+
+    let mut head = None;
+    if code == 0 {
+        let to_change_head_commands = [
+            "reset",
+            "checkout",
+            "commit",
+            "merge",
+            "rebase",
+            "ff",
+            "cherry-pick",
+        ]
+        .map(OsString::from)
+        .to_vec();
+
+        // check if any of head changing commands are in the passed args
+        if !args.args.intersect(to_change_head_commands).is_empty() {
+            let new_head = embedded_repo.get_head()?;
+            let parent_repo = Repo::new(Repo::new(&cwd).repo_top_level_dir()?);
+            let embed_path = diff_paths(embedded_repo.work_tree(), parent_repo.work_tree())
+                .unwrap_or_else(|| embedded_repo.work_tree().to_path_buf());
+            parent_repo.add_fild_to_embed_file(&embed_path.to_string_lossy(), "head", &new_head)?;
+            head = Some(new_head);
+        }
+    }
+    thread::sleep(Duration::from_millis(10));
+
+    if !format.is_human() {
+        io::stderr().write_all(&stderr)?;
+        let run = Run {
+            args: args
+                .args
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            embed: embedded_repo.work_tree().display().to_string(),
+            exit_code: code,
+            stdout: String::from_utf8_lossy(&stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+            head,
+        };
+        report::print(format, &run)?;
+    }
+    Ok(code)
+}
@@ -0,0 +1,63 @@
+use git_test_fixture::TestRepo;
+
+const EGIT: &str = env!("CARGO_BIN_EXE_egit");
+
+#[test]
+fn commit_updates_gitembed_head() {
+    let fixture = TestRepo::new("egit");
+    let embedded = fixture.embedded("vendor/lib.v2");
+    let head = embedded.commit_file("lib.txt", "v1", "Add lib");
+    fixture.gitembed("vendor/lib.v2", "https://example.com/lib.git", &head);
+    fixture.commit("Embed lib");
+
+    embedded.write("lib.txt", "v2");
+    let output = fixture
+        .command(EGIT)
+        .current_dir(embedded.path())
+        .args(["--", "commit", "--quiet", "-am", "Update lib"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let new_head = embedded.head();
+    assert_ne!(new_head, head);
+    let embeds = fixture.repo().get_embeds().unwrap();
+    assert_eq!(embeds[0].head, new_head);
+}
+
+#[test]
+fn outside_of_embedded_repository() {
+    let fixture = TestRepo::new("egit-outside");
+    let output = fixture.run(EGIT, &["--", "status"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is not an embedded directory"));
+}
+
+#[test]
+fn git_flags_reach_git() {
+    let fixture = TestRepo::new("egit-flags");
+    let embedded = fixture.embedded("vendor/lib");
+    let head = embedded.commit_file("lib.txt", "v1", "Add lib");
+
+    let output = fixture
+        .command(EGIT)
+        .current_dir(embedded.path())
+        .args(["log", "--format=%H"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("{head}\n")
+    );
+
+    let output = fixture
+        .command(EGIT)
+        .current_dir(embedded.path())
+        .args(["--format=json", "log", "--format=%H"])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains(&format!("\"stdout\": \"{head}\\n\"")));
+}
@@ -0,0 +1,802 @@
+use std::{
+    ffi::{OsStr, OsString},
+    io::{self, Write},
+    path::PathBuf,
+    process::Stdio,
+    thread,
+    time::Duration,
+};
+
+use clap::{CommandFactory, Parser};
+use colored::Colorize;
+use git_utils_shared::{
+    cli::GlobalArgs,
+    complete, egit,
+    embed::{EmbedEnty, EMBED_FILE},
+    file::{GitStatus, GitStatusFile},
+    report::{self, porcelain_line, Format, Report},
+    settings::flag,
+    GitError, Repo,
+};
+use serde::Serialize;
+use tabled::{Style, Table};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None, propagate_version = true)]
+struct Args {
+    #[clap(flatten)]
+    global: GlobalArgs,
+    #[clap(subcommand)]
+    action: Action,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Action {
+    Init,
+    /// Add an embedded repo.
+    Add(Add),
+    Remove(Remove),
+    // Fetch,
+    Status,
+    Update,
+    /// Tag every embedded repo with the current commit of this repo.
+    Tag(Tag),
+    /// Get a list of all embedded repo.
+    List,
+}
+
+#[derive(clap::Args, Debug)]
+struct Add {
+    /// Git url of repository to embed.
+    git_url: String,
+    /// Path to where code should be placed.
+    #[clap(validator(|path| match std::path::Path::new(&path).is_dir() {
+        true => Err("Folder already exists"),
+        false => Ok(())
+    }))]
+    project_path: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct Remove {
+    #[clap(validator(|path| match std::path::Path::new(&path).is_dir() {
+        true => Ok(()),
+        false => Err("Project folder does not exist")
+    }))]
+    project_path: String,
+    /// Also remove the files in the embedded repository, not just the embedded repository entry.
+    #[clap(long, short, takes_value = false)]
+    remove_all_files: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct Tag {
+    /// Tag name, with {project}, {embed}, {date} and {head} replaced [default:
+    /// excess.embed.tagFormat or {project}.{date}.{head}]
+    #[clap(long, value_name = "FORMAT")]
+    tag_format: Option<String>,
+    /// Push the tags, the default unless excess.embed.push is false
+    #[clap(long)]
+    push: bool,
+    /// Only create the tags
+    #[clap(long, overrides_with = "push")]
+    no_push: bool,
+}
+
+/// The clap definition of git-embed, for completions and man pages.
+pub fn command() -> clap::Command<'static> {
+    Args::command()
+}
+
+/// Paths of the embedded repositories listed in `.gitembed`.
+pub fn complete_value(path: &[&str], arg: &str, _prefix: &str) -> Vec<String> {
+    match (path, arg) {
+        (["remove"], "project-path") => Repo::current()
+            .and_then(|repo| repo.get_embeds())
+            .map(|embeds| embeds.into_iter().map(|embed| embed.path).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+/// Run git-embed with the command line `args`, the first one being the program name.
+///
+/// Exits the process when the tool fails; the multicall `git-excess` binary calls this too.
+pub fn main<I, T>(args: I)
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    complete::intercept(&args, command, complete_value);
+    if !egit::is_available() {
+        eprintln!(
+            "{} egit is not installed, git-embed depends on it.",
+            "error:".red().bold()
+        );
+        std::process::exit(1);
+    }
+
+    let args = Args::parse_from(args);
+    if let Err(err) = run(args) {
+        eprintln!("{} {}", "error:".red().bold(), err);
+        std::process::exit(err.exit_code());
+    }
+}
+
+fn run(args: Args) -> Result<(), GitError> {
+    let repo = Repo::current()?;
+    args.global.init(&repo)?;
+    let settings = repo.settings()?;
+    let format = settings.value("excess.embed.format", args.global.format)?;
+    use Action::*;
+    match args.action {
+        Add(add_args) => report::print(format, &add(&repo, &add_args, format)?),
+        Remove(remove_args) => report::print(format, &remove(&repo, &remove_args)?),
+        Init => report::print(format, &init(&repo, format)?),
+        // Fetch => fetch(),
+        Tag(tag_args) => {
+            let tagged = tag(
+                &repo,
+                &settings.value("excess.embed.tagFormat", tag_args.tag_format)?,
+                settings.bool("excess.embed.push", flag(tag_args.push, tag_args.no_push))?,
+                format,
+            )?;
+            report::print(format, &tagged)
+        }
+        Status => report::print(format, &status(&repo)?),
+        Update => report::print(format, &update(&repo, format)?),
+        List => report::print(format, &list_embed(&repo)?),
+    }
+}
+
+fn is_initialised(repo: &Repo, entry: &EmbedEnty) -> bool {
+    repo.work_tree().join(&entry.path).join(".egit").is_dir()
+}
+
+/// Progress messages go to stderr when stdout carries a machine readable report.
+fn progress(format: Format, message: &str) {
+    if format.is_human() {
+        println!("{message}");
+    } else {
+        eprintln!("{message}");
+    }
+}
+
+/// Where the output of egit goes, see [`progress`].
+fn egit_stdout(format: Format) -> Stdio {
+    if format.is_human() {
+        Stdio::inherit()
+    } else {
+        io::stderr().into()
+    }
+}
+
+/// One embedded project touched by an action.
+#[derive(Debug, Serialize)]
+struct Changed {
+    name: String,
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    /// False when the action was skipped for this project, e.g. because cloning failed.
+    ok: bool,
+}
+
+impl Changed {
+    fn new(entry: &EmbedEnty) -> Self {
+        Changed {
+            name: entry.name.clone(),
+            path: entry.path.clone(),
+            head: None,
+            tag: None,
+            ok: true,
+        }
+    }
+}
+
+/// What add, remove, init, tag and update did.
+#[derive(Debug, Serialize)]
+struct ActionReport {
+    action: &'static str,
+    embeds: Vec<Changed>,
+}
+
+impl Report for ActionReport {
+    fn human(&self, out: &mut dyn Write) -> io::Result<()> {
+        let done = self.embeds.iter().filter(|changed| changed.ok).count();
+        writeln!(
+            out,
+            "{}: {done} embedded project{}",
+            self.action.bold(),
+            if done == 1 { "" } else { "s" }
+        )?;
+        for changed in self.embeds.iter().filter(|changed| !changed.ok) {
+            writeln!(out, "{} {}", "failed:".red(), changed.name)?;
+        }
+        Ok(())
+    }
+
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()> {
+        for changed in &self.embeds {
+            porcelain_line(
+                out,
+                &[
+                    self.action,
+                    if changed.ok { "ok" } else { "failed" },
+                    &changed.name,
+                    &changed.path,
+                    changed.head.as_deref().unwrap_or(""),
+                    changed.tag.as_deref().unwrap_or(""),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn add(repo: &Repo, add_args: &Add, format: Format) -> Result<ActionReport, GitError> {
+    if let Err(err) = repo.clone_repo(&add_args.git_url, &add_args.project_path) {
+        progress(
+            format,
+            &format!("Failed to clone from: {}", &add_args.git_url),
+        );
+        return Err(err);
+    }
+    // todo: we need to make sure that path does not already exist, and that it is within the GIT_DIR
+    repo.add_fild_to_embed_file(&add_args.project_path, "url", &add_args.git_url)?;
+    repo.add_fild_to_embed_file(&add_args.project_path, "path", &add_args.project_path)?;
+
+    let project_path = repo.work_tree().join(&add_args.project_path);
+    std::fs::rename(project_path.join(".git"), project_path.join(".egit"))?;
+
+    let embed_head = repo.get_head_of_embed_project(&add_args.project_path)?;
+    repo.add_fild_to_embed_file(&add_args.project_path, "head", &embed_head)?;
+
+    let added = Changed {
+        name: add_args.project_path.clone(),
+        path: add_args.project_path.clone(),
+        head: Some(embed_head),
+        tag: None,
+        ok: true,
+    };
+    Ok(ActionReport {
+        action: "added",
+        embeds: vec![added],
+    })
+}
+
+fn remove(repo: &Repo, remove_args: &Remove) -> Result<ActionReport, GitError> {
+    let project_path = repo.work_tree().join(&remove_args.project_path);
+    let egit_path = project_path.join(".egit");
+    let mut embeds = Vec::new();
+    // if egit exist: folder is a subdirectory. Then remove embed entry from .gitembed
+    // Optional: remove folder itself
+    if egit_path.is_dir() {
+        let _ = std::fs::remove_dir_all(&egit_path);
+        if remove_args.remove_all_files {
+            let _ = std::fs::remove_dir_all(&project_path);
+        }
+        repo.remove_section_to_embed_file(&remove_args.project_path)?;
+        embeds.push(Changed {
+            name: remove_args.project_path.clone(),
+            path: remove_args.project_path.clone(),
+            head: None,
+            tag: None,
+            ok: true,
+        });
+    };
+    Ok(ActionReport {
+        action: "removed",
+        embeds,
+    })
+}
+
+fn init(repo: &Repo, format: Format) -> Result<ActionReport, GitError> {
+    let parent_repo = repo.repo_top_level_dir()?;
+    let tmp_folder = parent_repo.join("egit-tmp");
+    let mut embeds = Vec::new();
+
+    // dbg!(basename(&parent_repo.display().to_string(), '/'));
+    for entry in repo
+        .get_embeds()?
+        .iter()
+        .filter(|entry| !is_initialised(repo, entry))
+    {
+        progress(format, &format!("Setting up: {}", entry.name.cyan()));
+        let _ = std::fs::remove_dir_all(&tmp_folder);
+        let _ = std::fs::create_dir_all(&tmp_folder);
+        let repo_path = tmp_folder.join("repo");
+        let mut changed = Changed::new(entry);
+        if repo
+            .clone_repo(&entry.git_url, &repo_path.to_string_lossy())
+            .is_ok()
+        {
+            let _ = std::fs::rename(repo_path.join(".git"), repo_path.join(".egit"));
+
+            let embedded = repo.embed(&repo_path);
+            let egit = |args: Vec<&str>| embedded.run_egit_with(args, egit_stdout(format));
+            egit(vec!["reset", "--mixed", &entry.head])?;
+            egit(vec![
+                "checkout",
+                "-b",
+                &utils::basename(&parent_repo.display().to_string(), '/'),
+            ])?;
+
+            egit(vec!["branch", "-D", "master"])?;
+            changed.head = Some(entry.head.clone());
+        } else {
+            changed.ok = false;
+        }
+        embeds.push(changed);
+
+        thread::sleep(Duration::from_millis(10));
+        let _ = std::fs::remove_dir_all(&tmp_folder);
+    }
+    Ok(ActionReport {
+        action: "initialised",
+        embeds,
+    })
+}
+
+/// State of one initialised embedded project.
+#[derive(Serialize)]
+struct EmbedStatus {
+    name: String,
+    path: String,
+    /// Head recorded in `.gitembed`.
+    recorded_head: String,
+    /// Head the embedded repository is at.
+    head: String,
+    changes: Vec<GitStatusFile>,
+}
+
+#[derive(Serialize)]
+struct StatusReport {
+    embeds: Vec<EmbedStatus>,
+}
+
+/// Two letter code of `git status --short`.
+fn short_code(change: &GitStatusFile) -> String {
+    let code = |status: GitStatus| match status {
+        GitStatus::Unmodified => ' ',
+        status => status.code(),
+    };
+    format!("{}{}", code(change.index), code(change.worktree))
+}
+
+impl Report for StatusReport {
+    fn human(&self, out: &mut dyn Write) -> io::Result<()> {
+        for embed in &self.embeds {
+            writeln!(out, "Status of: {}", embed.name.cyan())?;
+            if embed.head == embed.recorded_head {
+                writeln!(out, "HEAD {}", embed.head)?;
+            } else {
+                writeln!(
+                    out,
+                    "HEAD {} ({} records {})",
+                    embed.head.yellow(),
+                    EMBED_FILE,
+                    embed.recorded_head
+                )?;
+            }
+            if embed.changes.is_empty() {
+                writeln!(out, "nothing to commit, working tree clean")?;
+            }
+            for change in &embed.changes {
+                writeln!(out, "{} {}", short_code(change), change.path.display())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()> {
+        for embed in &self.embeds {
+            porcelain_line(
+                out,
+                &[
+                    "embed",
+                    &embed.name,
+                    &embed.path,
+                    &embed.head,
+                    &embed.recorded_head,
+                ],
+            )?;
+            for change in &embed.changes {
+                porcelain_line(
+                    out,
+                    &[
+                        OsStr::new("change"),
+                        embed.name.as_ref(),
+                        short_code(change).as_ref(),
+                        change.path.as_os_str(),
+                    ],
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn status(repo: &Repo) -> Result<StatusReport, GitError> {
+    let parent_repo = repo.at(repo.repo_top_level_dir()?);
+    let mut embeds = Vec::new();
+    for entry in repo
+        .get_embeds()?
+        .iter()
+        .filter(|entry| is_initialised(repo, entry))
+    {
+        let embedded = parent_repo.embed(&entry.path);
+        embeds.push(EmbedStatus {
+            name: entry.name.clone(),
+            path: entry.path.clone(),
+            recorded_head: entry.head.clone(),
+            head: embedded.get_head()?,
+            changes: embedded.git_status(false)?,
+        });
+    }
+    Ok(StatusReport { embeds })
+}
+
+/// Expand the placeholders of a tag name format.
+fn tag_name(tag_format: &str, project: &str, embed: &str, date: &str, head: &str) -> String {
+    tag_format
+        .replace("{project}", project)
+        .replace("{embed}", embed)
+        .replace("{date}", date)
+        .replace("{head}", head)
+}
+
+fn tag(
+    repo: &Repo,
+    tag_format: &str,
+    push: bool,
+    format: Format,
+) -> Result<ActionReport, GitError> {
+    if let Some(paths) = repo.modified_files(&[PathBuf::from(EMBED_FILE)])? {
+        return Err(GitError::Dirty { paths });
+    }
+
+    let parent_repo = repo.repo_top_level_dir()?.display().to_string();
+    let project_name = utils::basename(&parent_repo, '/');
+    // a branch without commits has no date to put in the tag
+    let date = repo
+        .get_last_commit_time()?
+        .map(|date| date.format("%Y%m%d%H%M%S").to_string())
+        .unwrap_or_default();
+    let mut embeds = Vec::new();
+    for entry in repo.get_embeds()? {
+        let tag_name = tag_name(
+            tag_format,
+            &project_name,
+            &entry.name,
+            &date,
+            &repo.get_head()?,
+        );
+        progress(
+            format,
+            &format!("Tagging {} as {}", &project_name.cyan(), tag_name),
+        );
+        let embedded = repo.embed(&entry.path);
+        embedded.run_egit_with(vec!["tag", &tag_name], egit_stdout(format))?;
+        if push {
+            embedded.run_egit_with(vec!["push", "--tags"], egit_stdout(format))?;
+        }
+        embeds.push(Changed {
+            tag: Some(tag_name),
+            ..Changed::new(&entry)
+        });
+    }
+    Ok(ActionReport {
+        action: "tagged",
+        embeds,
+    })
+}
+
+fn update(repo: &Repo, format: Format) -> Result<ActionReport, GitError> {
+    let parent_repo = repo.at(repo.repo_top_level_dir()?);
+    let mut embeds = Vec::new();
+    for entry in repo
+        .get_embeds()?
+        .iter()
+        .filter(|entry| is_initialised(repo, entry))
+    {
+        progress(
+            format,
+            &format!("Updating {} to {}", entry.name.cyan(), entry.head.yellow()),
+        );
+
+        let embedded = parent_repo.embed(&entry.path);
+        let egit = |args: Vec<&str>| embedded.run_egit_with(args, egit_stdout(format));
+        egit(vec!["fetch", "--tags"])?;
+        egit(vec!["reset", "--mixed", &entry.head])?;
+        egit(vec!["status"])?;
+        embeds.push(Changed {
+            head: Some(entry.head.clone()),
+            ..Changed::new(entry)
+        });
+    }
+    Ok(ActionReport {
+        action: "updated",
+        embeds,
+    })
+}
+
+/// An entry of `.gitembed` and whether it is checked out.
+#[derive(Serialize)]
+struct EmbedState {
+    name: String,
+    path: String,
+    url: String,
+    head: String,
+    initialised: bool,
+}
+
+#[derive(Serialize)]
+struct ListReport {
+    #[serde(skip)]
+    entries: Vec<EmbedEnty>,
+    embeds: Vec<EmbedState>,
+}
+
+impl Report for ListReport {
+    fn human(&self, out: &mut dyn Write) -> io::Result<()> {
+        let table = Table::new(&self.entries).with(Style::modern());
+        writeln!(out, "{table}")
+    }
+
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()> {
+        for embed in &self.embeds {
+            porcelain_line(
+                out,
+                &[
+                    embed.name.as_str(),
+                    &embed.path,
+                    &embed.head,
+                    if embed.initialised { "yes" } else { "no" },
+                    &embed.url,
+                ],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn list_embed(repo: &Repo) -> Result<ListReport, GitError> {
+    let entries = repo.get_embeds()?;
+    let embeds = entries
+        .iter()
+        .map(|entry| EmbedState {
+            name: entry.name.clone(),
+            path: entry.path.clone(),
+            url: entry.git_url.clone(),
+            head: entry.head.clone(),
+            initialised: is_initialised(repo, entry),
+        })
+        .collect();
+    Ok(ListReport { entries, embeds })
+}
+
+mod utils {
+    use std::borrow::Cow;
+
+    pub fn basename<'a>(path: &'a str, sep: char) -> Cow<'a, str> {
+        let mut pieces = path.rsplit(sep);
+        match pieces.next() {
+            Some(p) => p.into(),
+            None => path.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git_test_fixture::TestRepo;
+    use git_utils_shared::{backend::Subprocess, log::LOG_FORMAT, runner::MockRunner};
+    use std::{path::Path, sync::Arc};
+
+    fn scripted(fixture: &TestRepo, mock: MockRunner) -> Repo {
+        fixture
+            .repo()
+            .clone()
+            .with_backend(Arc::new(Subprocess))
+            .with_runner(Arc::new(mock))
+    }
+
+    const TAG_FORMAT: &str = "{project}.{date}.{head}";
+
+    fn top_level(mock: MockRunner, fixture: &TestRepo) -> MockRunner {
+        mock.expect(&["git", "rev-parse", "--show-toplevel"])
+            .stdout(&format!("{}\n", fixture.path().display()))
+    }
+
+    #[test]
+    fn tag_refuses_dirty_gitembed() {
+        let fixture = TestRepo::new("embed-tag-dirty");
+        let mock = MockRunner::new()
+            .expect(&[
+                "git",
+                "--no-pager",
+                "diff",
+                "--name-only",
+                "-z",
+                "--",
+                ".gitembed",
+            ])
+            .stdout(".gitembed\0");
+        let mock = top_level(mock, &fixture);
+
+        let err = tag(&scripted(&fixture, mock), TAG_FORMAT, true, Format::Json).unwrap_err();
+        assert!(matches!(err, GitError::Dirty { paths } if paths == [Path::new(".gitembed")]));
+    }
+
+    #[test]
+    fn tag_every_embed() {
+        let fixture = TestRepo::new("embed-tag");
+        fixture.gitembed("lib", "https://example.com/lib.git", "1111111");
+        let project = fixture.path().file_name().unwrap().to_string_lossy();
+        let tag_name = format!("{project}.20220415052000.abcdef0");
+
+        let mock = MockRunner::new().expect(&[
+            "git",
+            "--no-pager",
+            "diff",
+            "--name-only",
+            "-z",
+            "--",
+            ".gitembed",
+        ]);
+        let mock = top_level(mock, &fixture)
+            .expect(&[
+                "git",
+                "log",
+                "-z",
+                "--no-color",
+                &format!("--format={LOG_FORMAT}"),
+                "--max-count=1",
+                "--end-of-options",
+                "HEAD",
+                "--",
+            ])
+            .stdout(concat!(
+                "abcdef0\0\0A\0a@example.com\02022-04-15T05:20:00+00:00\0",
+                "A\0a@example.com\02022-04-15T05:20:00+00:00\0Subject\0\0",
+            ))
+            .expect(&["git", "rev-parse", "--verify", "--end-of-options", "HEAD"])
+            .stdout("abcdef0\n")
+            .expect(&["egit", "--", "tag", &tag_name])
+            .env_removed("GIT_DIR")
+            .expect(&["egit", "--", "push", "--tags"]);
+
+        tag(&scripted(&fixture, mock), TAG_FORMAT, true, Format::Json).unwrap();
+    }
+
+    #[test]
+    fn tag_format_without_push() {
+        let fixture = TestRepo::new("embed-tag-format");
+        fixture.gitembed("lib", "https://example.com/lib.git", "1111111");
+
+        let mock = MockRunner::new().expect(&[
+            "git",
+            "--no-pager",
+            "diff",
+            "--name-only",
+            "-z",
+            "--",
+            ".gitembed",
+        ]);
+        let mock = top_level(mock, &fixture)
+            .expect(&[
+                "git",
+                "log",
+                "-z",
+                "--no-color",
+                &format!("--format={LOG_FORMAT}"),
+                "--max-count=1",
+                "--end-of-options",
+                "HEAD",
+                "--",
+            ])
+            .stdout(concat!(
+                "abcdef0\0\0A\0a@example.com\02022-04-15T05:20:00+00:00\0",
+                "A\0a@example.com\02022-04-15T05:20:00+00:00\0Subject\0\0",
+            ))
+            .expect(&["git", "rev-parse", "--verify", "--end-of-options", "HEAD"])
+            .stdout("abcdef0\n")
+            .expect(&["egit", "--", "tag", "release/lib-abcdef0"]);
+
+        tag(
+            &scripted(&fixture, mock),
+            "release/{embed}-{head}",
+            false,
+            Format::Json,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn update_skips_uninitialised() {
+        let fixture = TestRepo::new("embed-update");
+        fixture.gitembed("missing", "https://example.com/missing.git", "1111111");
+        fixture.embedded("lib");
+        fixture.gitembed("lib", "https://example.com/lib.git", "2222222");
+
+        let mock = top_level(MockRunner::new(), &fixture)
+            .expect(&["egit", "--", "fetch", "--tags"])
+            .expect(&["egit", "--", "reset", "--mixed", "2222222"])
+            .expect(&["egit", "--", "status"]);
+
+        update(&scripted(&fixture, mock), Format::Json).unwrap();
+    }
+
+    #[test]
+    fn update_stops_on_egit_failure() {
+        let fixture = TestRepo::new("embed-update-failure");
+        fixture.embedded("lib");
+        fixture.gitembed("lib", "https://example.com/lib.git", "2222222");
+
+        let mock = top_level(MockRunner::new(), &fixture)
+            .expect(&["egit", "--", "fetch", "--tags"])
+            .exits(128);
+
+        let err = update(&scripted(&fixture, mock), Format::Json).unwrap_err();
+        assert_eq!(err.exit_code(), 128);
+    }
+
+    #[test]
+    fn init_skips_failed_clones() {
+        let fixture = TestRepo::new("embed-init");
+        fixture.gitembed("broken", "https://example.com/broken.git", "1111111");
+        fixture.gitembed("lib", "https://example.com/lib.git", "2222222");
+        let project = fixture.path().file_name().unwrap().to_string_lossy();
+        let tmp_repo = fixture.path().join("egit-tmp/repo");
+        let tmp_repo = tmp_repo.to_string_lossy();
+
+        let mock = top_level(MockRunner::new(), &fixture)
+            .expect(&[
+                "git",
+                "clone",
+                "--quiet",
+                "https://example.com/broken.git",
+                &tmp_repo,
+            ])
+            .exits(128)
+            .stderr("fatal: repository not found")
+            .expect(&[
+                "git",
+                "clone",
+                "--quiet",
+                "https://example.com/lib.git",
+                &tmp_repo,
+            ])
+            .expect(&["egit", "--", "reset", "--mixed", "2222222"])
+            .expect(&["egit", "--", "checkout", "-b", &project])
+            .expect(&["egit", "--", "branch", "-D", "master"]);
+
+        let initialised = init(&scripted(&fixture, mock), Format::Json).unwrap();
+        let mut out = Vec::new();
+        report::write(&mut out, Format::Json, &initialised).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"{
+  "action": "initialised",
+  "embeds": [
+    {
+      "name": "broken",
+      "path": "broken",
+      "ok": false
+    },
+    {
+      "name": "lib",
+      "path": "lib",
+      "head": "2222222",
+      "ok": true
+    }
+  ]
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use git_test_fixture::{bin, TestRepo};
+
+const GIT_EMBED: &str = env!("CARGO_BIN_EXE_git-embed");
+
+#[test]
+fn list() {
+    // git-embed refuses to run without egit
+    bin("egit");
+    let fixture = TestRepo::new("embed-list");
+    let embedded = fixture.embedded("vendor/lib.v2");
+    let head = embedded.commit("Initial");
+    fixture.gitembed("vendor/lib.v2", "https://example.com/lib.git?ref=v2", &head);
+
+    let output = fixture.run(GIT_EMBED, &["list"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("vendor/lib.v2"));
+    assert!(stdout.contains(&head));
+}
+
+#[test]
+fn remove() {
+    bin("egit");
+    let fixture = TestRepo::new("embed-remove");
+    let kept = fixture.embedded("kept").commit("Kept");
+    let removed = fixture.embedded("removed").commit("Removed");
+    fixture.gitembed("kept", "https://example.com/kept.git", &kept);
+    fixture.gitembed("removed", "https://example.com/removed.git", &removed);
+
+    let output = fixture.run(GIT_EMBED, &["remove", "removed"]);
+    assert!(output.status.success());
+    assert!(!fixture.path().join("removed/.egit").exists());
+
+    let embeds = fixture.repo().get_embeds().unwrap();
+    assert_eq!(embeds.len(), 1);
+    assert_eq!(embeds[0].name, "kept");
+}
+
+#[test]
+fn list_json() {
+    bin("egit");
+    let fixture = TestRepo::new("embed-list-json");
+    let head = fixture.embedded("lib").commit("Initial");
+    fixture.gitembed("lib", "https://example.com/lib.git", &head);
+
+    let output = fixture.run(GIT_EMBED, &["list", "--format=json"]);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["embeds"][0]["name"], "lib");
+    assert_eq!(json["embeds"][0]["url"], "https://example.com/lib.git");
+    assert_eq!(json["embeds"][0]["head"], head.as_str());
+    assert_eq!(json["embeds"][0]["initialised"], true);
+}
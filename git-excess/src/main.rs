@@ -0,0 +1,447 @@
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::{CommandFactory, FromArgMatches, Parser};
+use colored::Colorize;
+use git_utils_shared::{
+    cli::GlobalArgs,
+    complete::{self, Shell, ValueCandidates},
+    man,
+    report::{self, lossy_path, lossy_paths, porcelain_line, Format, Report},
+    settings::{Setting, KEYS},
+    GitError, Repo,
+};
+use serde::Serialize;
+
+/// A tool built into this binary, run when it is called by the tool's name or as a subcommand.
+struct Tool {
+    name: &'static str,
+    subcommand: &'static str,
+    about: &'static str,
+    main: fn(Vec<OsString>),
+    command: fn() -> clap::Command<'static>,
+    complete_value: ValueCandidates,
+}
+
+const TOOLS: &[Tool] = &[
+    Tool {
+        name: "git-sdif",
+        subcommand: "sdif",
+        about: "Show the commits unique to each of two branches",
+        main: git_sdif::main::<Vec<OsString>, OsString>,
+        command: git_sdif::command,
+        complete_value: git_sdif::complete_value,
+    },
+    Tool {
+        name: "git-srep",
+        subcommand: "srep",
+        about: "Search and replace in the files tracked by git",
+        main: git_srep::main::<Vec<OsString>, OsString>,
+        command: git_srep::command,
+        complete_value: git_srep::complete_value,
+    },
+    Tool {
+        name: "git-embed",
+        subcommand: "embed",
+        about: "Manage the repositories embedded in this one",
+        main: git_embed::main::<Vec<OsString>, OsString>,
+        command: git_embed::command,
+        complete_value: git_embed::complete_value,
+    },
+    Tool {
+        name: "egit",
+        subcommand: "egit",
+        about: "Run git inside an embedded repository",
+        main: egit::main::<Vec<OsString>, OsString>,
+        command: egit::command,
+        complete_value: egit::complete_value,
+    },
+];
+
+/// Settings shared by the git-excess tools.
+///
+/// This binary also contains every tool: it runs one when called by its name, e.g. through a
+/// link created by `git-excess install`, or as `git-excess <tool> ...`.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None, propagate_version = true)]
+struct Args {
+    #[clap(flatten)]
+    global: GlobalArgs,
+    #[clap(subcommand)]
+    action: Action,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Action {
+    /// Show the excess.* settings in effect for the current repository.
+    Config(Config),
+    /// Link every tool to this binary in a directory, e.g. one on PATH.
+    Install(Install),
+    /// Print the completion script of every tool, e.g. `git-excess completions bash >>~/.bashrc`.
+    Completions(Completions),
+    /// Write the man page of every tool in a directory, e.g. ~/.local/share/man/man1.
+    Man(Man),
+}
+
+#[derive(clap::Args, Debug)]
+struct Config {
+    /// List every setting with the value that wins
+    #[clap(long, short, required_unless_present_any = ["key", "all"])]
+    list: bool,
+    /// List every value read, overridden ones included, lowest precedence first
+    #[clap(long, conflicts_with = "key")]
+    all: bool,
+    /// Also show where each value was set
+    #[clap(long)]
+    show_origin: bool,
+    /// Print the value of this setting, e.g. excess.srep.force; exits with 1 when it is not set
+    #[clap(conflicts_with = "list")]
+    key: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct Install {
+    /// Directory to create the links in, e.g. ~/.local/bin
+    #[clap(parse(from_os_str))]
+    dir: PathBuf,
+    /// Replace files that already exist with the name of a tool
+    #[clap(long, short)]
+    force: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct Completions {
+    #[clap(possible_values = Shell::NAMES)]
+    shell: Shell,
+}
+
+#[derive(clap::Args, Debug)]
+struct Man {
+    /// Directory to write the pages in
+    #[clap(parse(from_os_str))]
+    dir: PathBuf,
+}
+
+/// Values of the `excess.*` settings.
+#[derive(Serialize)]
+struct ConfigReport {
+    #[serde(skip)]
+    show_origin: bool,
+    /// Print the values alone, like `git config <key>` does.
+    #[serde(skip)]
+    values_only: bool,
+    settings: Vec<Setting>,
+}
+
+impl Report for ConfigReport {
+    fn human(&self, out: &mut dyn Write) -> io::Result<()> {
+        for setting in &self.settings {
+            if self.show_origin {
+                write!(out, "{}\t", setting.source().dimmed())?;
+            }
+            match self.values_only {
+                true => writeln!(out, "{}", setting.value)?,
+                false => writeln!(out, "{}={}", setting.key, setting.value)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()> {
+        for setting in &self.settings {
+            let scope = setting.scope.to_string();
+            porcelain_line(
+                out,
+                &[
+                    &setting.key,
+                    &setting.value,
+                    &scope,
+                    setting.origin.as_deref().unwrap_or_default(),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Links to this binary created by `git-excess install`.
+#[derive(Serialize)]
+struct InstallReport {
+    #[serde(serialize_with = "lossy_path")]
+    target: PathBuf,
+    links: Vec<Link>,
+}
+
+#[derive(Serialize)]
+struct Link {
+    name: &'static str,
+    #[serde(serialize_with = "lossy_path")]
+    path: PathBuf,
+    status: LinkStatus,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum LinkStatus {
+    Created,
+    Replaced,
+    /// Already pointing at this binary.
+    Unchanged,
+}
+
+impl LinkStatus {
+    fn name(self) -> &'static str {
+        match self {
+            LinkStatus::Created => "created",
+            LinkStatus::Replaced => "replaced",
+            LinkStatus::Unchanged => "unchanged",
+        }
+    }
+}
+
+impl Report for InstallReport {
+    fn human(&self, out: &mut dyn Write) -> io::Result<()> {
+        for link in &self.links {
+            let status = match link.status {
+                LinkStatus::Unchanged => link.status.name().dimmed(),
+                _ => link.status.name().green(),
+            };
+            writeln!(
+                out,
+                "{status:>9} {} -> {}",
+                link.path.display(),
+                self.target.display()
+            )?;
+        }
+        Ok(())
+    }
+
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()> {
+        for link in &self.links {
+            porcelain_line(
+                out,
+                &[
+                    OsStr::new(link.status.name()),
+                    OsStr::new(link.name),
+                    link.path.as_os_str(),
+                    self.target.as_os_str(),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Man pages written by `git-excess man`.
+#[derive(Serialize)]
+struct ManReport {
+    #[serde(serialize_with = "lossy_paths")]
+    pages: Vec<PathBuf>,
+}
+
+impl Report for ManReport {
+    fn human(&self, out: &mut dyn Write) -> io::Result<()> {
+        for page in &self.pages {
+            writeln!(out, "{}", page.display())?;
+        }
+        Ok(())
+    }
+
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()> {
+        for page in &self.pages {
+            porcelain_line(out, &[OsStr::new("page"), page.as_os_str()])?;
+        }
+        Ok(())
+    }
+}
+
+fn main() {
+    let mut args: Vec<OsString> = env::args_os().collect();
+    // git-embed runs egit, which is in this binary whether or not its link is installed
+    if let Ok(exe) = env::current_exe() {
+        git_utils_shared::egit::use_multicall(exe);
+    }
+
+    let called_as = args.first().map(Path::new).and_then(Path::file_stem);
+    if let Some(tool) = TOOLS
+        .iter()
+        .find(|tool| called_as == Some(OsStr::new(tool.name)))
+    {
+        return (tool.main)(args);
+    }
+    let subcommand = args.get(1);
+    if let Some(tool) = TOOLS
+        .iter()
+        .find(|tool| subcommand.map(OsString::as_os_str) == Some(OsStr::new(tool.subcommand)))
+    {
+        let mut tool_args = vec![OsString::from(tool.name)];
+        tool_args.extend(args.drain(2..));
+        return (tool.main)(tool_args);
+    }
+    complete::intercept(&args, command_with_tools, complete_value);
+
+    let tools: String = TOOLS
+        .iter()
+        .map(|tool| format!("\n    {:<10}{}", tool.subcommand, tool.about))
+        .collect();
+    let after_help = format!("TOOLS:{tools}");
+    let matches = Args::command()
+        .after_help(after_help.as_str())
+        .get_matches_from(args);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    match run(args) {
+        // like `git config <key>` for a key that is not set
+        Ok(false) => std::process::exit(1),
+        Ok(true) => {}
+        Err(err) => {
+            eprintln!("{} {}", "error:".red().bold(), err);
+            std::process::exit(err.exit_code());
+        }
+    }
+}
+
+/// Run the action, `false` when there was nothing to show.
+fn run(args: Args) -> Result<bool, GitError> {
+    args.global.init(&Repo::current()?)?;
+    let format = args.global.format.unwrap_or_default();
+    match args.action {
+        Action::Config(config_args) => config(&Repo::current()?, &config_args, format),
+        Action::Install(install_args) => install(&install_args, format),
+        Action::Completions(completions) => {
+            print!(
+                "{}",
+                complete::script(completions.shell, command_with_tools(), "git-excess")
+            );
+            for tool in TOOLS {
+                print!(
+                    "{}",
+                    complete::script(completions.shell, (tool.command)(), tool.name)
+                );
+            }
+            Ok(true)
+        }
+        Action::Man(man_args) => man_pages(&man_args.dir, format),
+    }
+}
+
+/// git-excess with the tools as subcommands, as completed after `git-excess`.
+fn command_with_tools() -> clap::Command<'static> {
+    Args::command().subcommands(
+        TOOLS
+            .iter()
+            .map(|tool| (tool.command)().name(tool.subcommand).about(tool.about)),
+    )
+}
+
+fn complete_value(path: &[&str], arg: &str, prefix: &str) -> Vec<String> {
+    if let Some(tool) = TOOLS
+        .iter()
+        .find(|tool| path.first() == Some(&tool.subcommand))
+    {
+        return (tool.complete_value)(&path[1..], arg, prefix);
+    }
+    match (path, arg) {
+        (["config"], "key") => KEYS.iter().map(|key| key.name.to_string()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Write the man pages of git-excess and every tool, and of their subcommands, in `dir`.
+fn man_pages(dir: &Path, format: Format) -> Result<bool, GitError> {
+    let commands = std::iter::once(("git-excess", Args::command()))
+        .chain(TOOLS.iter().map(|tool| (tool.name, (tool.command)())));
+    fs::create_dir_all(dir)?;
+    let mut pages = Vec::new();
+    for (name, command) in commands {
+        for (file_name, page) in man::pages(command.name(name))? {
+            let path = dir.join(file_name);
+            fs::write(&path, page)?;
+            pages.push(path);
+        }
+    }
+    report::print(format, &ManReport { pages })?;
+    Ok(true)
+}
+
+fn config(repo: &Repo, config_args: &Config, format: Format) -> Result<bool, GitError> {
+    let settings = repo.settings()?;
+    let values = match &config_args.key {
+        Some(key) => match settings.get(key) {
+            Some(setting) => vec![setting.clone()],
+            None => return Ok(false),
+        },
+        None if config_args.all => settings.all().to_vec(),
+        None => settings.effective().into_iter().cloned().collect(),
+    };
+    report::print(
+        format,
+        &ConfigReport {
+            show_origin: config_args.show_origin,
+            values_only: config_args.key.is_some(),
+            settings: values,
+        },
+    )?;
+    Ok(true)
+}
+
+/// Link every tool, and git-excess itself for `git excess`, to this binary in `install.dir`.
+fn install(install: &Install, format: Format) -> Result<bool, GitError> {
+    let target = env::current_exe()?.canonicalize()?;
+    if !install.dir.is_dir() {
+        return Err(GitError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} is not a directory", install.dir.display()),
+        )));
+    }
+
+    let names = TOOLS.iter().map(|tool| tool.name).chain(["git-excess"]);
+    // check every name first so that nothing is linked when one of them is taken
+    let mut links = Vec::new();
+    for name in names {
+        let path = install
+            .dir
+            .join(format!("{name}{}", env::consts::EXE_SUFFIX));
+        let status = if path.canonicalize().ok().as_ref() == Some(&target) {
+            LinkStatus::Unchanged
+        } else if fs::symlink_metadata(&path).is_ok() {
+            if !install.force {
+                return Err(GitError::Io(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!(
+                        "{} already exists, use --force to replace it",
+                        path.display()
+                    ),
+                )));
+            }
+            LinkStatus::Replaced
+        } else {
+            LinkStatus::Created
+        };
+        links.push(Link { name, path, status });
+    }
+    for link in &links {
+        if link.status == LinkStatus::Replaced {
+            fs::remove_file(&link.path)?;
+        }
+        if link.status != LinkStatus::Unchanged {
+            symlink(&target, &link.path)?;
+        }
+    }
+    report::print(format, &InstallReport { target, links })?;
+    Ok(true)
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, path: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, path)
+}
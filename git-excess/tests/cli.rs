@@ -0,0 +1,166 @@
+use std::{env, fs};
+
+use git_test_fixture::TestRepo;
+
+const EXCESS: &str = env!("CARGO_BIN_EXE_git-excess");
+
+#[test]
+fn config_list() {
+    let fixture = TestRepo::new("excess-config");
+    fixture.write(
+        ".gitembed",
+        "[excess \"embed\"]\n\tpush = false\n\tformat = json\n",
+    );
+    fixture.git(&["config", "excess.embed.format", "porcelain"]);
+    fixture.git(&["config", "excess.custom.key", "value"]);
+
+    let output = fixture.run(EXCESS, &["config", "--list"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("excess.sdif.format=human\n"), "{stdout}");
+    assert!(stdout.contains("\nexcess.embed.format=porcelain\n"));
+    assert!(stdout.contains("\nexcess.embed.push=false\n"));
+    assert!(stdout.ends_with("\nexcess.custom.key=value\n"));
+
+    let output = fixture.run(EXCESS, &["config", "--all", "--format=json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let scopes: Vec<&str> = json["settings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|setting| setting["key"] == "excess.embed.format")
+        .map(|setting| setting["scope"].as_str().unwrap())
+        .collect();
+    assert_eq!(scopes, ["default", "gitembed", "local"]);
+}
+
+#[test]
+fn config_key() {
+    let fixture = TestRepo::new("excess-config-key");
+
+    let output = fixture.run(EXCESS, &["config", "excess.srep.force"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "false\n");
+
+    let output = fixture.run(EXCESS, &["config", "--show-origin", "excess.srep.force"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "default\tfalse\n"
+    );
+
+    let output = fixture.run(EXCESS, &["config", "excess.unknown"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn tool_subcommands() {
+    let fixture = TestRepo::new("excess-tools");
+    fixture.commit("Initial");
+    fixture.branch("dev").commit("Dev work");
+    fixture.checkout("master");
+
+    let output = fixture.run(EXCESS, &["sdif", "--format=porcelain", "master", "dev"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("\tdev\t"));
+
+    // git-embed runs egit from this binary when it is not on PATH
+    let head = fixture.embedded("lib").commit("Embedded");
+    fixture.gitembed("lib", "https://example.com/lib.git", &head);
+    let output = fixture
+        .command(EXCESS)
+        .args(["embed", "list"])
+        .env("PATH", env::var_os("PATH").unwrap_or_default())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8(output.stdout).unwrap().contains(&head));
+}
+
+#[test]
+fn install() {
+    let fixture = TestRepo::new("excess-install");
+    fixture.commit("Initial");
+    let bin = fixture.path().join("bin");
+    fs::create_dir(&bin).unwrap();
+    let dir = bin.to_str().unwrap();
+
+    let output = fixture.run(EXCESS, &["install", "--format=porcelain", dir]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 5);
+    assert!(stdout.lines().all(|line| line.starts_with("created\t")));
+
+    // the links run the tool they are named after
+    let output = fixture.run(bin.join("git-srep"), &["--version"]);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("git-srep "));
+
+    let output = fixture.run(EXCESS, &["install", "--format=porcelain", dir]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.lines().all(|line| line.starts_with("unchanged\t")));
+
+    fs::remove_file(bin.join("egit")).unwrap();
+    fs::write(bin.join("egit"), "").unwrap();
+    let output = fixture.run(EXCESS, &["install", dir]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("already exists"));
+    let output = fixture.run(EXCESS, &["install", "--force", "--format=porcelain", dir]);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("replaced\tegit\t"));
+}
+
+#[test]
+fn completions() {
+    let fixture = TestRepo::new("excess-completions");
+    fixture.commit_file("src/main.rs", "", "Initial");
+    fixture.branch("dev");
+    let head = fixture.embedded("vendor/lib").commit("Embedded");
+    fixture.gitembed("vendor/lib", "https://example.com/lib.git", &head);
+
+    let complete = |args: &[&str]| {
+        let index = args.len().to_string();
+        let mut words = vec!["__complete", index.as_str(), "git-excess"];
+        words.extend(args);
+        let output = fixture.run(EXCESS, &words);
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert_eq!(complete(&["sdif", "d"]), "dev\n");
+    // possible values come from the generated script
+    assert_eq!(complete(&["sdif", "--format", "j"]), "");
+    assert_eq!(complete(&["srep", "old", "new", "s"]), "src/\n");
+    assert_eq!(complete(&["embed", "remove", ""]), "vendor/lib\n");
+    assert_eq!(
+        complete(&["config", "excess.srep.f"]),
+        "excess.srep.force\nexcess.srep.format\n"
+    );
+
+    let output = fixture.run(EXCESS, &["completions", "bash"]);
+    let script = String::from_utf8(output.stdout).unwrap();
+    assert!(script.contains("complete -F _git_sdif_values -o bashdefault -o default git-sdif\n"));
+    assert!(script.contains("_git_embed() {"));
+    let output = fixture.run(EXCESS, &["completions", "zsh"]);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("compdef _egit egit\n"));
+}
+
+#[test]
+fn man_pages() {
+    let fixture = TestRepo::new("excess-man");
+    let dir = fixture.path().join("man/man1");
+
+    let output = fixture.run(EXCESS, &["man", dir.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().lines().count() > 5);
+    assert!(dir.join("git-embed-remove.1").exists());
+    let page = fs::read_to_string(dir.join("git-sdif.1")).unwrap();
+    assert!(page.contains(".TH git-sdif 1"));
+    assert!(page.contains("\\fB\\-\\-date\\-format\\fR"));
+}
@@ -0,0 +1,103 @@
+//! `--conflicts`: whether merging the two branches would conflict, without merging them.
+
+use std::{
+    ffi::OsStr,
+    io::{self, Write},
+};
+
+use colored::Colorize;
+use serde::Serialize;
+
+use git_utils_shared::{
+    merge::Conflict,
+    report::{porcelain_line, Report},
+    GitError, Repo,
+};
+
+/// Conflicts of an in-memory merge of the second branch into the first one.
+#[derive(Serialize)]
+pub(crate) struct Conflicts {
+    /// The branches, in the order they were given.
+    branches: [String; 2],
+    /// Empty when the histories are unrelated, then merged as if they started from nothing.
+    merge_bases: Vec<String>,
+    /// Tree the merge would record, conflict markers included.
+    tree: String,
+    clean: bool,
+    conflicts: Vec<Conflict>,
+}
+
+impl Conflicts {
+    pub(crate) fn new(
+        repo: &Repo,
+        branch1: &str,
+        branch2: &str,
+        merge_bases: Vec<String>,
+    ) -> Result<Self, GitError> {
+        let merge = repo.merge_tree(branch1, branch2)?;
+        Ok(Conflicts {
+            branches: [branch1.to_string(), branch2.to_string()],
+            merge_bases,
+            clean: merge.is_clean(),
+            tree: merge.tree,
+            conflicts: merge.conflicts,
+        })
+    }
+
+    pub(crate) fn is_clean(&self) -> bool {
+        self.clean
+    }
+}
+
+impl Report for Conflicts {
+    fn human(&self, out: &mut dyn Write) -> io::Result<()> {
+        let [into, from] = &self.branches;
+        if self.clean {
+            return writeln!(
+                out,
+                "{} merges cleanly into {}",
+                from.bold().yellow(),
+                into.bold().yellow()
+            );
+        }
+        writeln!(
+            out,
+            "Merging {} into {} conflicts ({})",
+            from.bold().yellow(),
+            into.bold().yellow(),
+            self.conflicts.len()
+        )?;
+        writeln!(out)?;
+        let width = self
+            .conflicts
+            .iter()
+            .map(|conflict| conflict.kind.len())
+            .max()
+            .unwrap_or(0);
+        for conflict in &self.conflicts {
+            let paths: Vec<_> = conflict
+                .paths
+                .iter()
+                .map(|path| path.to_string_lossy())
+                .collect();
+            writeln!(
+                out,
+                "{} {}",
+                format!("{:<width$}", conflict.kind).red(),
+                paths.join(" ")
+            )?;
+        }
+        Ok(())
+    }
+
+    /// `tree <tree>`, then `conflict <type> <path>...` for every conflict.
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()> {
+        porcelain_line(out, &["tree", &self.tree])?;
+        for conflict in &self.conflicts {
+            let mut fields = vec![OsStr::new("conflict"), OsStr::new(&conflict.kind)];
+            fields.extend(conflict.paths.iter().map(|path| path.as_os_str()));
+            porcelain_line(out, &fields)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,329 @@
+//! `--all`: every local branch against its upstream and the default branch.
+
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use colored::{ColoredString, Colorize};
+use serde::Serialize;
+
+use git_utils_shared::{
+    backend::LocalBranch,
+    log::LogOptions,
+    report::{porcelain_line, Report},
+    GitError, Repo,
+};
+
+/// Order of the `--all` table, chosen with `--sort`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum SortKey {
+    #[default]
+    Name,
+    /// Most recent last commit first.
+    Date,
+    /// Most commits the default branch does not have first.
+    Ahead,
+    /// Most commits of the default branch missing first.
+    Behind,
+}
+
+impl SortKey {
+    /// Values accepted by `--sort`.
+    pub(crate) const NAMES: [&'static str; 4] = ["name", "date", "ahead", "behind"];
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "name" => Ok(SortKey::Name),
+            "date" => Ok(SortKey::Date),
+            "ahead" => Ok(SortKey::Ahead),
+            "behind" => Ok(SortKey::Behind),
+            _ => Err(format!(
+                "unknown sort key `{value}`, expected one of {}",
+                SortKey::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// What a branch needs attention for, the first that applies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Status {
+    /// The branch the others are compared to.
+    Default,
+    /// Every commit is in the default branch, the branch can be deleted.
+    Merged,
+    /// No commit for longer than `excess.sdif.staleDays`.
+    Stale,
+    /// Both the branch and its upstream have commits the other does not.
+    Diverged,
+    Active,
+}
+
+impl Status {
+    fn colored(self) -> ColoredString {
+        let name = self.to_string();
+        match self {
+            Status::Default => name.cyan(),
+            Status::Merged => name.green(),
+            Status::Stale => name.yellow(),
+            Status::Diverged => name.red().bold(),
+            Status::Active => name.normal(),
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Status::Default => "default",
+            Status::Merged => "merged",
+            Status::Stale => "stale",
+            Status::Diverged => "diverged",
+            Status::Active => "active",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The branch a local branch tracks.
+#[derive(Serialize)]
+struct Upstream {
+    name: String,
+    /// The upstream branch no longer exists, e.g. it was deleted on the remote.
+    gone: bool,
+    /// Commits the upstream does not have yet.
+    ahead: usize,
+    /// Commits of the upstream not in the branch yet.
+    behind: usize,
+}
+
+#[derive(Serialize)]
+struct Branch {
+    name: String,
+    head: String,
+    /// Committer date of the last commit.
+    date: DateTime<FixedOffset>,
+    upstream: Option<Upstream>,
+    /// Commits the default branch does not have.
+    ahead: usize,
+    /// Commits of the default branch not in this branch.
+    behind: usize,
+    /// Merge base with the default branch, `None` when the histories are unrelated.
+    merge_base: Option<String>,
+    status: Status,
+}
+
+/// Every local branch, compared to its upstream and to the default branch.
+#[derive(Serialize)]
+pub(crate) struct Dashboard {
+    default_branch: String,
+    stale_days: u64,
+    branches: Vec<Branch>,
+}
+
+/// The branch the dashboard compares to, looked up in this order:
+///
+/// 1. `default_branch`, from `--default-branch` or `excess.sdif.defaultBranch` as the caller
+///    resolved it through the settings
+/// 2. `init.defaultBranch`, when that branch exists
+/// 3. `main`, when it exists
+/// 4. `master`, when it exists
+pub(crate) fn default_branch(
+    repo: &Repo,
+    default_branch: String,
+    branches: &[LocalBranch],
+) -> Result<Option<String>, GitError> {
+    if !default_branch.is_empty() {
+        return Ok(Some(default_branch));
+    }
+    let configured = repo.backend().config_get(repo, "init.defaultBranch")?;
+    Ok(configured
+        .into_iter()
+        .chain(["main".to_string(), "master".to_string()])
+        .find(|name| branches.iter().any(|branch| &branch.name == name)))
+}
+
+impl Dashboard {
+    pub(crate) fn new(
+        repo: &Repo,
+        local: Vec<LocalBranch>,
+        default_branch: String,
+        stale_days: u64,
+        sort: SortKey,
+    ) -> Result<Self, GitError> {
+        let refs: HashSet<String> = repo.ref_names()?.into_iter().collect();
+        let now = Utc::now();
+        let mut branches = Vec::new();
+        for LocalBranch { name, upstream } in local {
+            let head = repo.show_commit(&name)?;
+            let upstream = match upstream {
+                Some(upstream) if refs.contains(&upstream) => Some(Upstream {
+                    ahead: repo.count(&[&name], &LogOptions::default().hide(&upstream))?,
+                    behind: repo.count(&[&upstream], &LogOptions::default().hide(&name))?,
+                    name: upstream,
+                    gone: false,
+                }),
+                Some(upstream) => Some(Upstream {
+                    name: upstream,
+                    gone: true,
+                    ahead: 0,
+                    behind: 0,
+                }),
+                None => None,
+            };
+            let ahead = repo.count(&[&name], &LogOptions::default().hide(&default_branch))?;
+            let behind = repo.count(&[&default_branch], &LogOptions::default().hide(&name))?;
+
+            let date = head.committer.date;
+            let age = now.signed_duration_since(date).num_days();
+            let status = if name == default_branch {
+                Status::Default
+            } else if ahead == 0 {
+                Status::Merged
+            } else if age > i64::try_from(stale_days).unwrap_or(i64::MAX) {
+                Status::Stale
+            } else if upstream
+                .as_ref()
+                .is_some_and(|upstream| upstream.ahead > 0 && upstream.behind > 0)
+            {
+                Status::Diverged
+            } else {
+                Status::Active
+            };
+            branches.push(Branch {
+                merge_base: repo.get_merge_base(&name, &default_branch)?,
+                name,
+                head: head.hash,
+                date,
+                upstream,
+                ahead,
+                behind,
+                status,
+            });
+        }
+
+        match sort {
+            SortKey::Name => branches.sort_by(|a, b| a.name.cmp(&b.name)),
+            SortKey::Date => branches.sort_by_key(|branch| Reverse(branch.date)),
+            SortKey::Ahead => branches.sort_by_key(|branch| Reverse(branch.ahead)),
+            SortKey::Behind => branches.sort_by_key(|branch| Reverse(branch.behind)),
+        }
+        Ok(Dashboard {
+            default_branch,
+            stale_days,
+            branches,
+        })
+    }
+}
+
+/// `+ahead -behind`
+fn divergence(ahead: usize, behind: usize) -> String {
+    format!("+{ahead} -{behind}")
+}
+
+impl Report for Dashboard {
+    fn human(&self, out: &mut dyn Write) -> io::Result<()> {
+        let header = [
+            "Branch".to_string(),
+            "Last commit".to_string(),
+            "Upstream".to_string(),
+            "+/-".to_string(),
+            format!("{} +/-", self.default_branch),
+        ];
+        let rows: Vec<[String; 5]> = self
+            .branches
+            .iter()
+            .map(|branch| {
+                let (upstream, upstream_divergence) = match &branch.upstream {
+                    Some(upstream) if upstream.gone => {
+                        (format!("{} (gone)", upstream.name), "".into())
+                    }
+                    Some(upstream) => (
+                        upstream.name.clone(),
+                        divergence(upstream.ahead, upstream.behind),
+                    ),
+                    None => ("-".to_string(), "".into()),
+                };
+                [
+                    branch.name.clone(),
+                    branch.date.format("%Y-%m-%d").to_string(),
+                    upstream,
+                    upstream_divergence,
+                    divergence(branch.ahead, branch.behind),
+                ]
+            })
+            .collect();
+        let mut widths = header.clone().map(|title| title.chars().count());
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let pad = |cells: &[String; 5]| -> Vec<String> {
+            cells
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect()
+        };
+
+        writeln!(
+            out,
+            "{}",
+            format!("{}  Status", pad(&header).join("  ")).bold()
+        )?;
+        for (branch, row) in self.branches.iter().zip(&rows) {
+            let cells = pad(row);
+            writeln!(
+                out,
+                "{}  {}  {}",
+                cells[0].yellow(),
+                cells[1..].join("  "),
+                branch.status.colored()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// `branch <name> <head> <upstream> <ahead> <behind> <ahead> <behind> <status>`, counts
+    /// against the upstream then against the default branch, `-` when there is no upstream or it
+    /// is gone.
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()> {
+        for branch in &self.branches {
+            let (upstream, ahead, behind) = match &branch.upstream {
+                Some(upstream) if !upstream.gone => (
+                    upstream.name.as_str(),
+                    upstream.ahead.to_string(),
+                    upstream.behind.to_string(),
+                ),
+                Some(upstream) => (upstream.name.as_str(), "-".to_string(), "-".to_string()),
+                None => ("-", "-".to_string(), "-".to_string()),
+            };
+            porcelain_line(
+                out,
+                &[
+                    "branch",
+                    &branch.name,
+                    &branch.head,
+                    upstream,
+                    &ahead,
+                    &behind,
+                    &branch.ahead.to_string(),
+                    &branch.behind.to_string(),
+                    &branch.status.to_string(),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+}
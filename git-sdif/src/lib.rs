@@ -0,0 +1,828 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    fmt,
+    io::{self, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use chrono::format::{Item, StrftimeItems};
+use clap::{ArgGroup, CommandFactory, ErrorKind, Parser};
+use colored::Colorize;
+use serde::Serialize;
+
+use dashboard::SortKey;
+use git_utils_shared::{
+    cli::GlobalArgs,
+    complete,
+    diff::FileStat,
+    log::{Commit, LogFilter, LogOptions},
+    report::{self, porcelain_line, Format, Report},
+    GitError, Repo,
+};
+use release_notes::GroupBy;
+use stat::Hotspot;
+
+mod conflicts;
+mod dashboard;
+mod matrix;
+mod range_diff;
+mod release_notes;
+mod stat;
+
+/// Compare two git branches or commits to see what the symmetric difference is.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+#[clap(group(ArgGroup::new("filter").multiple(true)))]
+struct Args {
+    /// The branches to compare, the second one defaulting to HEAD; with more than two, show
+    /// which of them contain each commit
+    #[clap(value_name = "BRANCH", required_unless_present = "all")]
+    branches: Vec<String>,
+    /// Instead of comparing branches, show every local branch against its upstream and the
+    /// default branch, marking the merged, stale and diverged ones
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "branches",
+            "stat",
+            "conflicts",
+            "range-diff",
+            "release-notes",
+            "filter"
+        ]
+    )]
+    all: bool,
+    /// Branch --all compares to [default: excess.sdif.defaultBranch, init.defaultBranch, main or
+    /// master]
+    #[clap(long, value_name = "BRANCH", requires = "all")]
+    default_branch: Option<String>,
+    /// Days without commits after which --all calls a branch stale [default:
+    /// excess.sdif.staleDays or 90]
+    #[clap(long, value_name = "DAYS", requires = "all")]
+    stale_days: Option<u64>,
+    /// Order of the --all table
+    #[clap(long, possible_values = SortKey::NAMES, requires = "all")]
+    sort: Option<SortKey>,
+    /// Same as --format=json
+    #[clap(long, conflicts_with = "format")]
+    json: bool,
+    /// Also list the files each branch changed since the merge base, and those changed on more
+    /// than one branch
+    #[clap(long)]
+    stat: bool,
+    /// Instead of the commits, list the conflicts merging the second branch into the first one
+    /// would have, exiting with 1 if there are any; the work tree and index are left alone
+    #[clap(long, conflicts_with_all = &["stat", "filter"])]
+    conflicts: bool,
+    /// Also pair the commits a rebase rewrote, by Change-Id trailer, "cherry picked from" line or
+    /// subject and author, and list the pairs like git range-diff, marking the changed ones
+    #[clap(long, conflicts_with = "conflicts")]
+    range_diff: bool,
+    /// Instead of the comparison, write Markdown release notes of the commits the first branch
+    /// would bring into the second one, grouped by Conventional Commits type or by issue key
+    /// like ABC-123
+    #[clap(
+        long,
+        value_name = "GROUP_BY",
+        possible_values = GroupBy::NAMES,
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "type",
+        conflicts_with_all = &["stat", "conflicts"]
+    )]
+    release_notes: Option<GroupBy>,
+    /// Only list commits whose author matches this regex
+    #[clap(long, value_name = "PATTERN", group = "filter")]
+    author: Option<String>,
+    /// Only list commits more recent than this date, e.g. "2 weeks ago"
+    #[clap(long, value_name = "DATE", group = "filter")]
+    since: Option<String>,
+    /// Only list commits older than this date
+    #[clap(long, value_name = "DATE", group = "filter")]
+    until: Option<String>,
+    /// Only list commits whose message matches this regex
+    #[clap(long, value_name = "PATTERN", group = "filter")]
+    grep: Option<String>,
+    /// Leave merge commits out of the lists
+    #[clap(long, group = "filter")]
+    no_merges: bool,
+    /// Only list commits changing these paths, given after `--`
+    #[clap(last = true, value_name = "PATH", parse(from_os_str), group = "filter")]
+    paths: Vec<PathBuf>,
+    /// strftime format of commit dates [default: excess.sdif.dateFormat or git's]
+    #[clap(long, value_name = "FORMAT")]
+    date_format: Option<DateFormat>,
+    #[clap(flatten)]
+    global: GlobalArgs,
+}
+
+impl Args {
+    /// The commit filters, applied to both sides and their counts.
+    fn filter(&self) -> LogFilter {
+        LogFilter {
+            author: self.author.clone(),
+            since: self.since.clone(),
+            until: self.until.clone(),
+            grep: self.grep.clone(),
+            no_merges: self.no_merges,
+            paths: self.paths.clone(),
+        }
+    }
+}
+
+fn cli_divider(out: &mut dyn Write, message: &str) -> io::Result<()> {
+    writeln!(out)?;
+    writeln!(
+        out,
+        "-------------------------------------------------------------------------------"
+    )?;
+    writeln!(out, "{message}")?;
+    writeln!(out)
+}
+
+/// A strftime format checked up front, chrono panics on invalid ones while formatting.
+#[derive(Clone, Debug)]
+struct DateFormat(String);
+
+impl FromStr for DateFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        if StrftimeItems::new(format).any(|item| item == Item::Error) {
+            return Err(format!("`{format}` is not a valid strftime format"));
+        }
+        Ok(DateFormat(format.to_string()))
+    }
+}
+
+/// One line per commit: hash, author, date and subject.
+fn print_commits(out: &mut dyn Write, commits: &[&Commit], date_format: &str) -> io::Result<()> {
+    for commit in commits {
+        writeln!(
+            out,
+            "{} {} [{}] {}",
+            commit.short_hash().green(),
+            format!("({})", commit.author.name).red(),
+            commit.author.date.format(date_format),
+            commit.subject.blue()
+        )?;
+    }
+    writeln!(out)
+}
+
+/// Abbreviated hash for display.
+fn short(hash: &str) -> &str {
+    &hash[..hash.len().min(7)]
+}
+
+/// A commit the way `git log -1` shows it.
+fn print_commit(out: &mut dyn Write, commit: &Commit, date_format: &str) -> io::Result<()> {
+    writeln!(out, "{}", format!("commit {}", commit.hash).yellow())?;
+    if commit.is_merge() {
+        let parents: Vec<&str> = commit.parents.iter().map(|p| short(p)).collect();
+        writeln!(out, "Merge: {}", parents.join(" "))?;
+    }
+    writeln!(
+        out,
+        "Author: {} <{}>",
+        commit.author.name, commit.author.email
+    )?;
+    writeln!(out, "Date:   {}", commit.author.date.format(date_format))?;
+    writeln!(out)?;
+    writeln!(out, "    {}", commit.subject)?;
+    if !commit.body.is_empty() {
+        writeln!(out)?;
+        for line in commit.body.lines() {
+            writeln!(out, "    {line}")?;
+        }
+    }
+    Ok(())
+}
+
+/// First line of a report: the merge base, or how many there are.
+fn print_merge_bases(out: &mut dyn Write, merge_bases: &[Commit]) -> io::Result<()> {
+    match merge_bases {
+        [] => writeln!(
+            out,
+            "{}: the histories are unrelated",
+            "No merge base".red()
+        ),
+        [merge_base] => writeln!(out, "Found {}: {}", "Merge base".cyan(), merge_base.hash),
+        _ => {
+            let hashes: Vec<&str> = merge_bases.iter().map(|base| base.hash.as_str()).collect();
+            writeln!(
+                out,
+                "Found {} {} (criss-cross merges): {}",
+                merge_bases.len(),
+                "merge bases".cyan(),
+                hashes.join(" ")
+            )
+        }
+    }
+}
+
+/// Every merge base the way `git log -1` shows it, under a divider naming what they are common to.
+fn print_common_ancestors(
+    out: &mut dyn Write,
+    merge_bases: &[Commit],
+    of: &str,
+    date_format: &str,
+) -> io::Result<()> {
+    match merge_bases.len() {
+        1 => cli_divider(out, &format!("Common anchestor of {of}"))?,
+        count => cli_divider(out, &format!("Common anchestors of {of} ({count})"))?,
+    }
+    for (index, merge_base) in merge_bases.iter().enumerate() {
+        if index > 0 {
+            writeln!(out)?;
+        }
+        print_commit(out, merge_base, date_format)?;
+    }
+    Ok(())
+}
+
+/// The commits without parents of branch `name`, for histories without a merge base.
+fn print_roots<'a>(
+    out: &mut dyn Write,
+    name: &str,
+    roots: impl Iterator<Item = &'a Commit>,
+    date_format: &str,
+) -> io::Result<()> {
+    cli_divider(out, &format!("Root commits of {}", name.bold().yellow()))?;
+    for (index, root) in roots.enumerate() {
+        if index > 0 {
+            writeln!(out)?;
+        }
+        print_commit(out, root, date_format)?;
+    }
+    Ok(())
+}
+
+/// Commits of one branch that the other branch does not have.
+#[derive(Serialize)]
+struct Side {
+    /// Branch or revision as given on the command line.
+    name: String,
+    /// Commit the branch points to.
+    head: String,
+    /// Number of commits, how far the branch diverged from the merge base; only those matching
+    /// the filters when there are some.
+    count: usize,
+    /// Number of commits whose change the other branch does not have either.
+    missing: usize,
+    commits: Vec<SideCommit>,
+    /// Commits without parents, the start of the history when it is unrelated to the other one.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    roots: Vec<String>,
+    /// Files changed since the merge base, with `--stat`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<Vec<FileStat>>,
+}
+
+impl Side {
+    /// Commits that are not cherry-picked to the other branch.
+    fn missing(&self) -> impl Iterator<Item = &Commit> {
+        self.commits
+            .iter()
+            .filter(|commit| commit.equivalent.is_none())
+            .map(|commit| &commit.commit)
+    }
+}
+
+#[derive(Serialize)]
+struct SideCommit {
+    #[serde(flatten)]
+    commit: Commit,
+    /// Commit of the other branch making the same change, when it was cherry-picked or, with
+    /// `--range-diff`, rewritten.
+    equivalent: Option<String>,
+}
+
+/// How the commits of an [`Equivalent`] pair were matched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum MatchedBy {
+    /// Same patch ID, the only one without `--range-diff`.
+    PatchId,
+    /// Same `Change-Id` trailer, as Gerrit adds.
+    ChangeId,
+    /// One names the other in the `(cherry picked from commit ...)` line of `git cherry-pick -x`.
+    CherryPickedFrom,
+    /// Same subject and author email.
+    Subject,
+}
+
+impl fmt::Display for MatchedBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MatchedBy::PatchId => "patch-id",
+            MatchedBy::ChangeId => "change-id",
+            MatchedBy::CherryPickedFrom => "cherry-picked-from",
+            MatchedBy::Subject => "subject",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What differs between the commits of an [`Equivalent`] pair.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Change {
+    /// The patch IDs differ, e.g. conflicts were resolved differently.
+    Content,
+    /// The subject or body differ.
+    Message,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Change::Content => "content",
+            Change::Message => "message",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A change both branches have, under a different hash on each.
+#[derive(Serialize)]
+struct Equivalent {
+    /// Patch ID of both commits, `None` unless they were matched by it.
+    patch_id: Option<String>,
+    /// The commit of each side, in the order of `sides`.
+    commits: [String; 2],
+    subject: String,
+    matched_by: MatchedBy,
+    /// Empty when the commits make the same change with the same message.
+    changes: Vec<Change>,
+}
+
+#[derive(Serialize)]
+struct SymmetricDifference {
+    /// First of `merge_bases`, the one `--stat` diffs from; `None` when the histories are
+    /// unrelated.
+    merge_base: Option<Commit>,
+    /// Every best common ancestor, more than one after criss-cross merges.
+    merge_bases: Vec<Commit>,
+    sides: [Side; 2],
+    equivalent: Vec<Equivalent>,
+    /// Files both branches changed, with `--stat`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hotspots: Option<Vec<Hotspot>>,
+    /// Files that differ between the two branches, when the histories are unrelated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tree_diff: Option<Vec<FileStat>>,
+    /// With `--range-diff`, list the pairs like git range-diff instead of the cherry-picks.
+    #[serde(skip)]
+    range_diff: bool,
+    #[serde(skip)]
+    date_format: String,
+}
+
+/// Pair the commits of both sides not paired yet whose keys are equal, newest first on each side.
+fn pair_by(
+    sides: &mut [Side; 2],
+    patch_ids: &HashMap<String, String>,
+    matched_by: MatchedBy,
+    left_key: impl Fn(&Commit) -> Option<String>,
+    right_key: impl Fn(&Commit) -> Option<String>,
+) -> Vec<Equivalent> {
+    let mut right: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, commit) in sides[1].commits.iter().enumerate().rev() {
+        if commit.equivalent.is_some() {
+            continue;
+        }
+        if let Some(key) = right_key(&commit.commit) {
+            right.entry(key).or_default().push(index);
+        }
+    }
+
+    let mut pairs = Vec::new();
+    for left in 0..sides[0].commits.len() {
+        if sides[0].commits[left].equivalent.is_some() {
+            continue;
+        }
+        let Some(key) = left_key(&sides[0].commits[left].commit) else {
+            continue;
+        };
+        let Some(right) = right.get_mut(&key).and_then(Vec::pop) else {
+            continue;
+        };
+        let one = &sides[0].commits[left].commit;
+        let two = &sides[1].commits[right].commit;
+        let patch_id = [one, two].map(|commit| patch_ids.get(&commit.hash));
+        let mut changes = Vec::new();
+        if patch_id[0] != patch_id[1] {
+            changes.push(Change::Content);
+        }
+        if (&one.subject, &one.body) != (&two.subject, &two.body) {
+            changes.push(Change::Message);
+        }
+        let pair = Equivalent {
+            patch_id: patch_id[0]
+                .filter(|_| matched_by == MatchedBy::PatchId)
+                .cloned(),
+            commits: [one.hash.clone(), two.hash.clone()],
+            subject: one.subject.clone(),
+            matched_by,
+            changes,
+        };
+        sides[0].commits[left].equivalent = Some(pair.commits[1].clone());
+        sides[1].commits[right].equivalent = Some(pair.commits[0].clone());
+        pairs.push(pair);
+    }
+    pairs
+}
+
+/// Pair the commits of both sides that have the same patch ID, then with `rewritten` those
+/// [`range_diff::REWRITTEN`] matches.
+fn pair_equivalent(
+    sides: &mut [Side; 2],
+    patch_ids: &HashMap<String, String>,
+    rewritten: bool,
+) -> Vec<Equivalent> {
+    let patch_id = |commit: &Commit| patch_ids.get(&commit.hash).cloned();
+    let mut pairs = pair_by(sides, patch_ids, MatchedBy::PatchId, patch_id, patch_id);
+    if rewritten {
+        for (matched_by, [left_key, right_key]) in range_diff::REWRITTEN {
+            pairs.extend(pair_by(sides, patch_ids, matched_by, left_key, right_key));
+        }
+    }
+    for side in sides {
+        side.missing = side.missing().count();
+    }
+    pairs
+}
+
+impl Report for SymmetricDifference {
+    fn human(&self, out: &mut dyn Write) -> io::Result<()> {
+        print_merge_bases(out, &self.merge_bases)?;
+        for side in &self.sides {
+            cli_divider(
+                out,
+                &format!(
+                    "Commits unique to {} ({})",
+                    side.name.bold().yellow(),
+                    side.missing
+                ),
+            )?;
+            let missing: Vec<&Commit> = side.missing().collect();
+            print_commits(out, &missing, &self.date_format)?;
+            if let Some(files) = &side.files {
+                stat::print_stat(out, files, self.hotspots.as_deref().unwrap_or_default())?;
+            }
+        }
+        if self.range_diff {
+            cli_divider(
+                out,
+                &format!(
+                    "Range diff of {} and {}",
+                    self.sides[0].name.bold().yellow(),
+                    self.sides[1].name.bold().yellow()
+                ),
+            )?;
+            range_diff::print(out, &self.sides, &self.equivalent)?;
+        } else if !self.equivalent.is_empty() {
+            cli_divider(
+                out,
+                &format!(
+                    "Cherry-picked between {} and {} ({})",
+                    self.sides[0].name.bold().yellow(),
+                    self.sides[1].name.bold().yellow(),
+                    self.equivalent.len()
+                ),
+            )?;
+            for pair in &self.equivalent {
+                writeln!(
+                    out,
+                    "{} = {} {}",
+                    short(&pair.commits[0]).green(),
+                    short(&pair.commits[1]).green(),
+                    pair.subject.blue()
+                )?;
+            }
+            writeln!(out)?;
+        }
+        if let Some(hotspots) = self.hotspots.as_deref().filter(|h| !h.is_empty()) {
+            cli_divider(
+                out,
+                &format!("Changed on both branches ({})", hotspots.len()),
+            )?;
+            stat::print_hotspots(out, hotspots)?;
+        }
+        let [one, two] = &self.sides;
+        if let Some(files) = &self.tree_diff {
+            for side in &self.sides {
+                let roots = side
+                    .commits
+                    .iter()
+                    .filter(|commit| side.roots.contains(&commit.commit.hash))
+                    .map(|commit| &commit.commit);
+                print_roots(out, &side.name, roots, &self.date_format)?;
+            }
+            cli_divider(
+                out,
+                &format!("Files that differ between {} and {}", one.name, two.name),
+            )?;
+            return stat::print_stat(out, files, &[]);
+        }
+        print_common_ancestors(
+            out,
+            &self.merge_bases,
+            &format!("{} and {}", one.name, two.name),
+            &self.date_format,
+        )
+    }
+
+    /// `merge-base <hash>` for every merge base, `unique <branch> <hash> <subject>` for every
+    /// commit, then the cherry-picks and rewritten commits, `--stat` files and, for unrelated
+    /// histories, `root <branch> <hash>` and `diff <insertions> <deletions> <path>`.
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()> {
+        for merge_base in &self.merge_bases {
+            porcelain_line(out, &["merge-base", &merge_base.hash])?;
+        }
+        for side in &self.sides {
+            for commit in side.missing() {
+                porcelain_line(out, &["unique", &side.name, &commit.hash, &commit.subject])?;
+            }
+        }
+        for pair in &self.equivalent {
+            if pair.matched_by == MatchedBy::PatchId {
+                porcelain_line(
+                    out,
+                    &[
+                        "equivalent",
+                        &pair.commits[0],
+                        &pair.commits[1],
+                        &pair.subject,
+                    ],
+                )?;
+            } else {
+                range_diff::porcelain(out, pair)?;
+            }
+        }
+        for side in &self.sides {
+            stat::porcelain_files(
+                out,
+                &["file", &side.name],
+                side.files.as_deref().unwrap_or_default(),
+            )?;
+        }
+        stat::porcelain_hotspots(out, self.hotspots.as_deref().unwrap_or_default())?;
+        for side in &self.sides {
+            for root in &side.roots {
+                porcelain_line(out, &["root", &side.name, root])?;
+            }
+        }
+        stat::porcelain_files(
+            out,
+            &["diff"],
+            self.tree_diff.as_deref().unwrap_or_default(),
+        )
+    }
+}
+
+/// The clap definition of git-sdif, for completions and man pages.
+pub fn command() -> clap::Command<'static> {
+    Args::command()
+}
+
+/// Branch and tag names for the branches to compare, tracked paths after `--`.
+pub fn complete_value(_path: &[&str], arg: &str, prefix: &str) -> Vec<String> {
+    match arg {
+        "branches" | "default-branch" => Repo::current()
+            .and_then(|repo| repo.ref_names())
+            .unwrap_or_default(),
+        "paths" => Repo::current()
+            .and_then(|repo| repo.tracked_files())
+            .map(|files| complete::path_candidates(&files, prefix))
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+/// Run git-sdif with the command line `args`, the first one being the program name.
+///
+/// Exits the process when the tool fails; the multicall `git-excess` binary calls this too.
+pub fn main<I, T>(args: I)
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    complete::intercept(&args, command, complete_value);
+    let args = Args::parse_from(args);
+
+    if let Err(err) = run(&args) {
+        eprintln!("{} {}", "error:".red().bold(), err);
+        std::process::exit(err.exit_code());
+    }
+}
+
+fn run(args: &Args) -> Result<(), GitError> {
+    let repo = Repo::current()?;
+    args.global.init(&repo)?;
+    let settings = repo.settings()?;
+    let format = settings.value(
+        "excess.sdif.format",
+        args.global.format.or(args.json.then_some(Format::Json)),
+    )?;
+    let date_format = settings.value("excess.sdif.dateFormat", args.date_format.clone())?;
+    if args.all {
+        let local = repo.local_branches()?;
+        let default_branch =
+            settings.value("excess.sdif.defaultBranch", args.default_branch.clone())?;
+        let Some(default_branch) = dashboard::default_branch(&repo, default_branch, &local)? else {
+            Args::command()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    "there is no main or master branch, choose one with --default-branch",
+                )
+                .exit();
+        };
+        let stale_days = settings.value("excess.sdif.staleDays", args.stale_days)?;
+        let sort = args.sort.unwrap_or_default();
+        let report = dashboard::Dashboard::new(&repo, local, default_branch, stale_days, sort)?;
+        return report::print(format, &report);
+    }
+    let mut branches: Vec<&str> = args.branches.iter().map(String::as_str).collect();
+    if branches.len() == 1 {
+        branches.push("HEAD");
+    }
+    if args.conflicts && branches.len() > 2 {
+        Args::command()
+            .error(ErrorKind::TooManyValues, "--conflicts merges two branches")
+            .exit();
+    }
+    if args.range_diff && branches.len() > 2 {
+        Args::command()
+            .error(ErrorKind::TooManyValues, "--range-diff pairs two branches")
+            .exit();
+    }
+    if args.release_notes.is_some() && branches.len() > 2 {
+        Args::command()
+            .error(
+                ErrorKind::TooManyValues,
+                "--release-notes merges one branch into another",
+            )
+            .exit();
+    }
+    let merge_bases = repo.get_merge_bases(&branches)?;
+    if branches.len() > 2 {
+        let report = matrix::Matrix::new(
+            &repo,
+            &branches,
+            &merge_bases,
+            args.stat,
+            args.filter(),
+            date_format.0,
+        )?;
+        return report::print(format, &report);
+    }
+    let (branch1, branch2) = (branches[0], branches[1]);
+    if args.conflicts {
+        let report = conflicts::Conflicts::new(&repo, branch1, branch2, merge_bases)?;
+        report::print(format, &report)?;
+        if !report.is_clean() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let side = |name: &str, other: &str| -> Result<Side, GitError> {
+        let commits = repo.log(&[name], &LogOptions::default().hide(other))?;
+        // like `git diff A...B`, from the first merge base
+        let files = match merge_bases.first() {
+            Some(merge_base) if args.stat => Some(repo.diff_stat(merge_base, name)?),
+            _ => None,
+        };
+        let roots = commits
+            .iter()
+            .filter(|commit| commit.parents.is_empty())
+            .map(|commit| commit.hash.clone())
+            .collect();
+        Ok(Side {
+            name: name.to_string(),
+            head: repo.rev_parse(&format!("{name}^{{commit}}"))?,
+            count: commits.len(),
+            missing: commits.len(),
+            commits: commits
+                .into_iter()
+                .map(|commit| SideCommit {
+                    commit,
+                    equivalent: None,
+                })
+                .collect(),
+            roots,
+            files,
+        })
+    };
+    let mut sides = [side(branch1, branch2)?, side(branch2, branch1)?];
+    // like `git log --cherry-mark`, only worth it when both sides have commits
+    let mut equivalent = match sides.iter().all(|side| side.count > 0) {
+        true => {
+            let range = format!("{branch1}...{branch2}");
+            let patch_ids = repo.patch_ids(&[&range], &LogOptions::default())?;
+            pair_equivalent(&mut sides, &patch_ids, args.range_diff)
+        }
+        false => Vec::new(),
+    };
+    // filtered after pairing, a cherry-pick does not have to match the filters to count
+    let filter = args.filter();
+    if !filter.is_empty() {
+        let mut shown = Vec::new();
+        for (name, other) in [(branch1, branch2), (branch2, branch1)] {
+            let options = LogOptions::default().hide(other).filter(filter.clone());
+            let commits = repo.log(&[name], &options)?;
+            shown.push(
+                commits
+                    .into_iter()
+                    .map(|commit| commit.hash)
+                    .collect::<HashSet<_>>(),
+            );
+        }
+        for (side, shown) in sides.iter_mut().zip(&shown) {
+            side.commits
+                .retain(|commit| shown.contains(&commit.commit.hash));
+            side.count = side.commits.len();
+            side.missing = side.missing().count();
+        }
+        equivalent.retain(|pair| {
+            shown
+                .iter()
+                .zip(&pair.commits)
+                .any(|(shown, hash)| shown.contains(hash))
+        });
+    }
+    if let Some(group_by) = args.release_notes {
+        let missing: Vec<&Commit> = sides[0].missing().collect();
+        let names = [branch1.to_string(), branch2.to_string()];
+        let report = release_notes::ReleaseNotes::new(names, &missing, group_by);
+        return report::print(format, &report);
+    }
+    let hotspots = sides
+        .iter()
+        .map(|side| Some((side.name.as_str(), side.files.as_deref()?)))
+        .collect::<Option<Vec<_>>>()
+        .map(|branches| stat::hotspots(&branches));
+    // without a merge base there is nothing to count changes from, compare the trees instead
+    let tree_diff = match merge_bases.is_empty() {
+        true => Some(repo.diff_stat(branch1, branch2)?),
+        false => None,
+    };
+    let merge_bases = merge_bases
+        .iter()
+        .map(|merge_base| repo.show_commit(merge_base))
+        .collect::<Result<Vec<_>, _>>()?;
+    let report = SymmetricDifference {
+        merge_base: merge_bases.first().cloned(),
+        merge_bases,
+        sides,
+        equivalent,
+        hotspots,
+        tree_diff,
+        range_diff: args.range_diff,
+        date_format: date_format.0,
+    };
+    report::print(format, &report)
+}
+
+#[cfg(test)]
+mod tests {
+    use git_test_fixture::TestRepo;
+    use git_utils_shared::log::{Commit, LogOptions};
+
+    #[test]
+    fn it_works() {
+        let fixture = TestRepo::new("sdif");
+        let repo = fixture.repo();
+        let base = fixture.commit("A");
+
+        // commit in dev
+        fixture.branch("dev");
+        fixture.commit("D");
+        fixture.commit("E");
+
+        // commit in master
+        fixture.checkout("master");
+        fixture.commit("B");
+        fixture.commit("C");
+
+        let subjects = |commits: Vec<Commit>| -> Vec<String> {
+            commits.into_iter().map(|commit| commit.subject).collect()
+        };
+        let master = repo
+            .log(&["master"], &LogOptions::default().hide("dev"))
+            .unwrap();
+        assert_eq!(subjects(master), vec!["C", "B"]);
+
+        let dev = repo.log(&["master..dev"], &LogOptions::default()).unwrap();
+        assert_eq!(subjects(dev), vec!["E", "D"]);
+
+        let common_key = repo.get_merge_base("dev", "master").unwrap().unwrap();
+        assert_eq!(common_key, base);
+        let common_commit = repo.show_commit(&common_key).unwrap();
+        assert_eq!(common_commit.subject, "A");
+    }
+}
@@ -0,0 +1,236 @@
+//! Comparison of more than two branches: which of them contain each commit.
+
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+
+use colored::Colorize;
+use serde::Serialize;
+
+use git_utils_shared::{
+    diff::FileStat,
+    log::{Commit, LogFilter, LogOptions},
+    report::{porcelain_line, Report},
+    GitError, Repo,
+};
+
+use super::{
+    cli_divider, print_common_ancestors, print_merge_bases, print_roots, short,
+    stat::{self, Hotspot},
+};
+
+/// A branch of the comparison.
+#[derive(Serialize)]
+struct Branch {
+    /// Branch or revision as given on the command line.
+    name: String,
+    /// Commit the branch points to.
+    head: String,
+    /// Commits without parents, the start of the history when it is unrelated to the others.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    roots: Vec<String>,
+    /// Files changed since the merge base, with `--stat`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<Vec<FileStat>>,
+}
+
+#[derive(Serialize)]
+struct Row {
+    #[serde(flatten)]
+    commit: Commit,
+    /// Names of the branches containing the commit, in command line order.
+    branches: Vec<String>,
+}
+
+/// Commits that some of the branches have and others do not.
+#[derive(Serialize)]
+pub(crate) struct Matrix {
+    /// First of `merge_bases`, the one `--stat` diffs from; `None` when the histories are
+    /// unrelated.
+    merge_base: Option<Commit>,
+    /// Every best common ancestor of all the branches.
+    merge_bases: Vec<Commit>,
+    branches: Vec<Branch>,
+    /// Newest first, commits contained in every branch or not matching the filters left out.
+    commits: Vec<Row>,
+    /// Files changed on more than one branch, with `--stat`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hotspots: Option<Vec<Hotspot>>,
+    #[serde(skip)]
+    date_format: String,
+}
+
+impl Matrix {
+    pub(crate) fn new(
+        repo: &Repo,
+        names: &[&str],
+        merge_bases: &[String],
+        stat: bool,
+        filter: LogFilter,
+        date_format: String,
+    ) -> Result<Self, GitError> {
+        let hidden = merge_bases
+            .iter()
+            .fold(LogOptions::default(), |options, merge_base| {
+                options.hide(merge_base)
+            });
+        let mut branches = Vec::new();
+        let mut reachable: Vec<HashSet<String>> = Vec::new();
+        for name in names {
+            let commits = repo.log(&[name], &hidden)?;
+            branches.push(Branch {
+                name: name.to_string(),
+                head: repo.rev_parse(&format!("{name}^{{commit}}"))?,
+                roots: commits
+                    .iter()
+                    .filter(|commit| commit.parents.is_empty())
+                    .map(|commit| commit.hash.clone())
+                    .collect(),
+                files: match merge_bases.first() {
+                    Some(merge_base) if stat => Some(repo.diff_stat(merge_base, name)?),
+                    _ => None,
+                },
+            });
+            reachable.push(commits.into_iter().map(|commit| commit.hash).collect());
+        }
+
+        let mut commits = Vec::new();
+        for commit in repo.log(names, &hidden.clone().filter(filter))? {
+            let containing: Vec<String> = names
+                .iter()
+                .zip(&reachable)
+                .filter(|(_, reachable)| reachable.contains(&commit.hash))
+                .map(|(name, _)| name.to_string())
+                .collect();
+            // with criss-cross merges some commits past the merge base are in every branch
+            if containing.len() < names.len() {
+                commits.push(Row {
+                    commit,
+                    branches: containing,
+                });
+            }
+        }
+
+        let hotspots = branches
+            .iter()
+            .map(|branch| Some((branch.name.as_str(), branch.files.as_deref()?)))
+            .collect::<Option<Vec<_>>>()
+            .map(|files| stat::hotspots(&files));
+        let merge_bases = merge_bases
+            .iter()
+            .map(|merge_base| repo.show_commit(merge_base))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Matrix {
+            merge_base: merge_bases.first().cloned(),
+            merge_bases,
+            branches,
+            commits,
+            hotspots,
+            date_format,
+        })
+    }
+}
+
+impl Report for Matrix {
+    fn human(&self, out: &mut dyn Write) -> io::Result<()> {
+        print_merge_bases(out, &self.merge_bases)?;
+        cli_divider(
+            out,
+            &format!("Commits not in every branch ({})", self.commits.len()),
+        )?;
+
+        // one column per branch, headed by its name
+        let header: Vec<String> = self
+            .branches
+            .iter()
+            .map(|branch| branch.name.bold().yellow().to_string())
+            .collect();
+        writeln!(out, "{}", header.join("  "))?;
+        for row in &self.commits {
+            let marks: Vec<String> = self
+                .branches
+                .iter()
+                .map(|branch| {
+                    let width = branch.name.chars().count();
+                    match row.branches.contains(&branch.name) {
+                        true => format!("{:<width$}", "*").green().to_string(),
+                        false => format!("{:<width$}", "-").dimmed().to_string(),
+                    }
+                })
+                .collect();
+            writeln!(
+                out,
+                "{}  {} {} [{}] {}",
+                marks.join("  "),
+                short(&row.commit.hash).green(),
+                format!("({})", row.commit.author.name).red(),
+                row.commit.author.date.format(&self.date_format),
+                row.commit.subject.blue()
+            )?;
+        }
+        writeln!(out)?;
+
+        let hotspots = self.hotspots.as_deref().unwrap_or_default();
+        for branch in &self.branches {
+            if let Some(files) = &branch.files {
+                cli_divider(
+                    out,
+                    &format!("Files changed on {}", branch.name.bold().yellow()),
+                )?;
+                stat::print_stat(out, files, hotspots)?;
+            }
+        }
+        if !hotspots.is_empty() {
+            cli_divider(
+                out,
+                &format!("Changed on more than one branch ({})", hotspots.len()),
+            )?;
+            stat::print_hotspots(out, hotspots)?;
+        }
+
+        if self.merge_bases.is_empty() {
+            for branch in &self.branches {
+                let roots = self
+                    .commits
+                    .iter()
+                    .filter(|row| branch.roots.contains(&row.commit.hash))
+                    .map(|row| &row.commit);
+                print_roots(out, &branch.name, roots, &self.date_format)?;
+            }
+            return Ok(());
+        }
+        print_common_ancestors(out, &self.merge_bases, "all branches", &self.date_format)
+    }
+
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()> {
+        for merge_base in &self.merge_bases {
+            porcelain_line(out, &["merge-base", &merge_base.hash])?;
+        }
+        for row in &self.commits {
+            porcelain_line(
+                out,
+                &[
+                    "commit",
+                    &row.commit.hash,
+                    &row.branches.join(" "),
+                    &row.commit.subject,
+                ],
+            )?;
+        }
+        for branch in &self.branches {
+            stat::porcelain_files(
+                out,
+                &["file", &branch.name],
+                branch.files.as_deref().unwrap_or_default(),
+            )?;
+        }
+        stat::porcelain_hotspots(out, self.hotspots.as_deref().unwrap_or_default())?;
+        for branch in &self.branches {
+            for root in &branch.roots {
+                porcelain_line(out, &["root", &branch.name, root])?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+//! `--range-diff`: the commits a rebase rewrote, paired and listed like `git range-diff`.
+
+use std::io::{self, Write};
+
+use colored::{ColoredString, Colorize};
+
+use git_utils_shared::{log::Commit, report::porcelain_line};
+
+use super::{short, Equivalent, MatchedBy, Side};
+
+type Key = fn(&Commit) -> Option<String>;
+
+/// What rewritten commits are paired by once the patch IDs are, strongest first, with the key of
+/// the left and the right side. Merges are left out like git range-diff does.
+pub(crate) const REWRITTEN: [(MatchedBy, [Key; 2]); 4] = [
+    (MatchedBy::ChangeId, [change_id, change_id]),
+    (MatchedBy::CherryPickedFrom, [hash, cherry_picked_from]),
+    (MatchedBy::CherryPickedFrom, [cherry_picked_from, hash]),
+    (MatchedBy::Subject, [subject, subject]),
+];
+
+fn change_id(commit: &Commit) -> Option<String> {
+    if commit.is_merge() {
+        return None;
+    }
+    commit.trailer("Change-Id").map(str::to_string)
+}
+
+fn hash(commit: &Commit) -> Option<String> {
+    (!commit.is_merge()).then(|| commit.hash.clone())
+}
+
+/// The commit `git cherry-pick -x` copied this one from.
+fn cherry_picked_from(commit: &Commit) -> Option<String> {
+    if commit.is_merge() {
+        return None;
+    }
+    commit.body.lines().rev().find_map(|line| {
+        let hash = line.strip_prefix("(cherry picked from commit ")?;
+        hash.strip_suffix(')').map(str::to_string)
+    })
+}
+
+fn subject(commit: &Commit) -> Option<String> {
+    if commit.is_merge() {
+        return None;
+    }
+    Some(format!("{}\0{}", commit.author.email, commit.subject))
+}
+
+/// `<number>:  <hash>` of one side, dashes for the side a commit is missing from.
+fn column(width: usize, position: Option<usize>, hash: Option<&str>) -> String {
+    let number = position.map_or("-".to_string(), |position| (position + 1).to_string());
+    let hash = match hash {
+        Some(hash) => short(hash).green(),
+        None => "-------".dimmed(),
+    };
+    format!("{number:>width$}:  {hash}")
+}
+
+/// The commits of both sides oldest first and merges left out, like `git range-diff`: `=` for pairs
+/// making the same change with the same message, `!` for the others, `<` and `>` for commits of
+/// only the left or the right side.
+pub(crate) fn print(
+    out: &mut dyn Write,
+    sides: &[Side; 2],
+    pairs: &[Equivalent],
+) -> io::Result<()> {
+    let [left, right] = sides.each_ref().map(|side| {
+        side.commits
+            .iter()
+            .map(|commit| &commit.commit)
+            .filter(|commit| !commit.is_merge())
+            .rev()
+            .collect::<Vec<_>>()
+    });
+    let width = left.len().max(right.len()).to_string().len();
+    let position = |commits: &[&Commit], hash: &str| commits.iter().position(|c| c.hash == hash);
+    let partner = |side: usize, hash: &str| pairs.iter().find(|pair| pair.commits[side] == hash);
+
+    let only = |out: &mut dyn Write, side: usize, index: usize, commit: &Commit| {
+        let (marker, left, right): (ColoredString, _, _) = match side {
+            0 => (
+                "<".red(),
+                column(width, Some(index), Some(&commit.hash)),
+                column(width, None, None),
+            ),
+            _ => (
+                ">".green(),
+                column(width, None, None),
+                column(width, Some(index), Some(&commit.hash)),
+            ),
+        };
+        writeln!(out, "{left} {marker} {right} {}", commit.subject.blue())
+    };
+    let paired = |out: &mut dyn Write, pair: &Equivalent| {
+        let marker = match pair.changes.is_empty() {
+            true => "=".normal(),
+            false => "!".yellow().bold(),
+        };
+        let mut note = String::new();
+        if pair.matched_by != MatchedBy::PatchId || !pair.changes.is_empty() {
+            let changes: Vec<String> = pair.changes.iter().map(ToString::to_string).collect();
+            note = match changes.is_empty() {
+                true => format!(" ({})", pair.matched_by),
+                false => format!(" ({}; {} changed)", pair.matched_by, changes.join(", ")),
+            };
+        }
+        writeln!(
+            out,
+            "{} {marker} {} {}{}",
+            column(
+                width,
+                position(&left, &pair.commits[0]),
+                Some(&pair.commits[0])
+            ),
+            column(
+                width,
+                position(&right, &pair.commits[1]),
+                Some(&pair.commits[1])
+            ),
+            pair.subject.blue(),
+            note.dimmed()
+        )
+    };
+
+    let mut shown = vec![false; left.len()];
+    for (index, commit) in right.iter().enumerate() {
+        let Some(pair) = partner(1, &commit.hash) else {
+            only(out, 1, index, commit)?;
+            continue;
+        };
+        if let Some(partner_index) = position(&left, &pair.commits[0]) {
+            // commits only the left side has go before the next pair, like git range-diff
+            for earlier in 0..partner_index {
+                if !shown[earlier] && partner(0, &left[earlier].hash).is_none() {
+                    only(out, 0, earlier, left[earlier])?;
+                    shown[earlier] = true;
+                }
+            }
+            shown[partner_index] = true;
+        }
+        paired(out, pair)?;
+    }
+    for (index, commit) in left.iter().enumerate() {
+        if shown[index] {
+            continue;
+        }
+        match partner(0, &commit.hash) {
+            Some(pair) => paired(out, pair)?,
+            None => only(out, 0, index, commit)?,
+        }
+    }
+    writeln!(out)
+}
+
+/// `rewritten <left> <right> <matched-by> <changes> <subject>`, the changes comma separated or `-`.
+pub(crate) fn porcelain(out: &mut dyn Write, pair: &Equivalent) -> io::Result<()> {
+    let changes: Vec<String> = pair.changes.iter().map(ToString::to_string).collect();
+    let changes = match changes.is_empty() {
+        true => "-".to_string(),
+        false => changes.join(","),
+    };
+    porcelain_line(
+        out,
+        &[
+            "rewritten",
+            &pair.commits[0],
+            &pair.commits[1],
+            &pair.matched_by.to_string(),
+            &changes,
+            &pair.subject,
+        ],
+    )
+}
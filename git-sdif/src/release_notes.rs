@@ -0,0 +1,337 @@
+//! `--release-notes`: Markdown notes of the commits the first branch would bring in.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use serde::Serialize;
+
+use git_utils_shared::{
+    log::Commit,
+    report::{porcelain_line, Report},
+};
+
+use super::short;
+
+/// How `--release-notes` groups the commits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum GroupBy {
+    /// Conventional Commits type of the subject, e.g. `feat` or `fix`.
+    #[default]
+    Type,
+    /// First issue key of the message, e.g. `ABC-123`.
+    Issue,
+}
+
+impl GroupBy {
+    /// Values accepted by `--release-notes`.
+    pub(crate) const NAMES: [&'static str; 2] = ["type", "issue"];
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "type" => Ok(GroupBy::Type),
+            "issue" => Ok(GroupBy::Issue),
+            _ => Err(format!(
+                "unknown grouping `{value}`, expected one of {}",
+                GroupBy::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Conventional Commits types and their section titles, in the order the sections are listed.
+const TYPES: [(&str, &str); 11] = [
+    ("feat", "Features"),
+    ("fix", "Bug Fixes"),
+    ("perf", "Performance Improvements"),
+    ("revert", "Reverts"),
+    ("docs", "Documentation"),
+    ("refactor", "Code Refactoring"),
+    ("test", "Tests"),
+    ("build", "Build System"),
+    ("ci", "Continuous Integration"),
+    ("chore", "Chores"),
+    ("style", "Styles"),
+];
+
+const BREAKING: &str = "Breaking Changes";
+const OTHER: &str = "Other Changes";
+
+/// A subject like `feat(auth)!: support SSO`.
+#[derive(Debug, PartialEq, Eq)]
+struct Conventional<'a> {
+    kind: &'a str,
+    scope: Option<&'a str>,
+    /// Marked with `!`.
+    breaking: bool,
+    description: &'a str,
+}
+
+fn parse_conventional(subject: &str) -> Option<Conventional<'_>> {
+    let (prefix, description) = subject.split_once(": ")?;
+    let (prefix, breaking) = match prefix.strip_suffix('!') {
+        Some(prefix) => (prefix, true),
+        None => (prefix, false),
+    };
+    let (kind, scope) = match prefix.split_once('(') {
+        Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?)),
+        None => (prefix, None),
+    };
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(Conventional {
+        kind,
+        scope,
+        breaking,
+        description: description.trim(),
+    })
+}
+
+/// Issue keys like `ABC-123` in `text`, without duplicates, in order of appearance.
+fn issue_keys(text: &str) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    for word in text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '-')) {
+        let Some((project, number)) = word.split_once('-') else {
+            continue;
+        };
+        let is_project = project.len() > 1
+            && project.starts_with(|c: char| c.is_ascii_uppercase())
+            && project
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+        let is_number = !number.is_empty() && number.chars().all(|c| c.is_ascii_digit());
+        if is_project && is_number && !keys.iter().any(|key| key == word) {
+            keys.push(word.to_string());
+        }
+    }
+    keys
+}
+
+/// Sort key of an issue key, so that `ABC-9` comes before `ABC-10`.
+fn issue_order(key: &str) -> (&str, u64) {
+    let (project, number) = key.split_once('-').unwrap_or((key, ""));
+    (project, number.parse().unwrap_or(u64::MAX))
+}
+
+/// One line of the notes.
+#[derive(Serialize)]
+struct Note {
+    hash: String,
+    subject: String,
+    /// Subject without the Conventional Commits prefix.
+    description: String,
+    scope: Option<String>,
+    /// Marked with `!` or a `BREAKING CHANGE` trailer.
+    breaking: bool,
+    issues: Vec<String>,
+    author: String,
+}
+
+#[derive(Serialize)]
+struct Section {
+    title: String,
+    notes: Vec<Note>,
+}
+
+#[derive(Serialize)]
+struct Author {
+    name: String,
+    email: String,
+    commits: usize,
+}
+
+/// Release notes of the commits of `branches[0]` that `branches[1]` does not have.
+#[derive(Serialize)]
+pub(crate) struct ReleaseNotes {
+    branches: [String; 2],
+    group_by: GroupBy,
+    sections: Vec<Section>,
+    /// Most commits first.
+    authors: Vec<Author>,
+}
+
+impl ReleaseNotes {
+    /// Notes of `commits`, newest first as listed, merges left out.
+    pub(crate) fn new(branches: [String; 2], commits: &[&Commit], group_by: GroupBy) -> Self {
+        let mut sections: BTreeMap<(usize, String), Vec<Note>> = BTreeMap::new();
+        let mut authors: Vec<Author> = Vec::new();
+        for commit in commits.iter().rev().filter(|commit| !commit.is_merge()) {
+            // a prefix like `Note: ` is not a Conventional Commits type
+            let conventional = parse_conventional(&commit.subject).filter(|conventional| {
+                TYPES
+                    .iter()
+                    .any(|(kind, _)| kind.eq_ignore_ascii_case(conventional.kind))
+            });
+            let breaking = conventional.as_ref().is_some_and(|c| c.breaking)
+                || commit.trailer("BREAKING CHANGE").is_some()
+                || commit.trailer("BREAKING-CHANGE").is_some();
+            let issues = issue_keys(&format!("{}\n{}", commit.subject, commit.body));
+
+            // sections sort by rank, then by title
+            let section = match group_by {
+                GroupBy::Type if breaking => (0, BREAKING.to_string()),
+                GroupBy::Type => conventional
+                    .as_ref()
+                    .and_then(|c| {
+                        TYPES
+                            .iter()
+                            .position(|(kind, _)| kind.eq_ignore_ascii_case(c.kind))
+                    })
+                    .map(|index| (index + 1, TYPES[index].1.to_string()))
+                    .unwrap_or((TYPES.len() + 1, OTHER.to_string())),
+                GroupBy::Issue => match issues.first() {
+                    Some(key) => (0, key.clone()),
+                    None => (1, OTHER.to_string()),
+                },
+            };
+            sections.entry(section).or_default().push(Note {
+                hash: commit.hash.clone(),
+                subject: commit.subject.clone(),
+                description: conventional
+                    .as_ref()
+                    .map_or(&commit.subject[..], |c| c.description)
+                    .to_string(),
+                scope: conventional.and_then(|c| c.scope).map(str::to_string),
+                breaking,
+                issues,
+                author: commit.author.name.clone(),
+            });
+
+            match authors
+                .iter_mut()
+                .find(|author| author.email == commit.author.email)
+            {
+                Some(author) => author.commits += 1,
+                None => authors.push(Author {
+                    name: commit.author.name.clone(),
+                    email: commit.author.email.clone(),
+                    commits: 1,
+                }),
+            }
+        }
+
+        let mut sections: Vec<((usize, String), Vec<Note>)> = sections.into_iter().collect();
+        if group_by == GroupBy::Issue {
+            sections.sort_by(|((a_rank, a), _), ((b_rank, b), _)| {
+                (a_rank, issue_order(a)).cmp(&(b_rank, issue_order(b)))
+            });
+        }
+        authors.sort_by(|a, b| b.commits.cmp(&a.commits).then(a.name.cmp(&b.name)));
+        ReleaseNotes {
+            branches,
+            group_by,
+            sections: sections
+                .into_iter()
+                .map(|((_, title), notes)| Section { title, notes })
+                .collect(),
+            authors,
+        }
+    }
+}
+
+impl Report for ReleaseNotes {
+    /// Markdown, meant to be pasted as is, so never colored.
+    fn human(&self, out: &mut dyn Write) -> io::Result<()> {
+        let [from, into] = &self.branches;
+        let count: usize = self.sections.iter().map(|s| s.notes.len()).sum();
+        writeln!(out, "# Release notes")?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "Changes in `{from}` not in `{into}` ({count} commit{}).",
+            if count == 1 { "" } else { "s" }
+        )?;
+        for section in &self.sections {
+            writeln!(out)?;
+            writeln!(out, "## {}", section.title)?;
+            writeln!(out)?;
+            for note in &section.notes {
+                let text = match self.group_by {
+                    GroupBy::Type => match &note.scope {
+                        Some(scope) => format!("**{scope}:** {}", note.description),
+                        None => note.description.clone(),
+                    },
+                    GroupBy::Issue => note.subject.clone(),
+                };
+                writeln!(out, "- {text} ({}, {})", short(&note.hash), note.author)?;
+            }
+        }
+        if !self.authors.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "## Authors")?;
+            writeln!(out)?;
+            for author in &self.authors {
+                writeln!(out, "- {} ({})", author.name, author.commits)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `note <section> <hash> <subject>` for every commit, then `author <name> <email> <commits>`.
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()> {
+        for section in &self.sections {
+            for note in &section.notes {
+                porcelain_line(out, &["note", &section.title, &note.hash, &note.subject])?;
+            }
+        }
+        for author in &self.authors {
+            porcelain_line(
+                out,
+                &[
+                    "author",
+                    &author.name,
+                    &author.email,
+                    &author.commits.to_string(),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conventional() {
+        assert_eq!(
+            parse_conventional("feat(auth)!: support SSO"),
+            Some(Conventional {
+                kind: "feat",
+                scope: Some("auth"),
+                breaking: true,
+                description: "support SSO",
+            })
+        );
+        assert_eq!(parse_conventional("fix: crash").unwrap().scope, None);
+        assert_eq!(parse_conventional("Update docs"), None);
+        assert_eq!(
+            parse_conventional("Note: it works"),
+            Some(Conventional {
+                kind: "Note",
+                scope: None,
+                breaking: false,
+                description: "it works",
+            })
+        );
+        assert_eq!(parse_conventional("ABC-12: fix"), None);
+    }
+
+    #[test]
+    fn issues() {
+        assert_eq!(
+            issue_keys("ABC-12 Fix it\n\nSee PROJ2-7, ABC-12 and x-1, A-1, abc-3"),
+            ["ABC-12", "PROJ2-7"]
+        );
+        assert!(issue_order("ABC-9") < issue_order("ABC-10"));
+    }
+}
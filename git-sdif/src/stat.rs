@@ -0,0 +1,146 @@
+//! `--stat`: the files each branch changed since the merge base.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use colored::Colorize;
+use serde::Serialize;
+
+use git_utils_shared::{
+    diff::FileStat,
+    report::{lossy_path, porcelain_line},
+};
+
+/// Widest `+`/`-` bar, longer ones are scaled down like `git diff --stat` does.
+const BAR_WIDTH: usize = 50;
+
+/// A file changed on more than one branch, where merging them may conflict.
+#[derive(Serialize)]
+pub(crate) struct Hotspot {
+    #[serde(serialize_with = "lossy_path")]
+    path: PathBuf,
+    /// Names of the branches that changed the file.
+    branches: Vec<String>,
+}
+
+/// The files changed on more than one of `branches`, sorted by path.
+pub(crate) fn hotspots(branches: &[(&str, &[FileStat])]) -> Vec<Hotspot> {
+    let mut touched: BTreeMap<&Path, Vec<String>> = BTreeMap::new();
+    for (name, files) in branches {
+        for file in *files {
+            touched
+                .entry(&file.path)
+                .or_default()
+                .push(name.to_string());
+        }
+    }
+    touched
+        .into_iter()
+        .filter(|(_, branches)| branches.len() > 1)
+        .map(|(path, branches)| Hotspot {
+            path: path.to_path_buf(),
+            branches,
+        })
+        .collect()
+}
+
+/// The files like `git diff --stat`, hotspots in red.
+pub(crate) fn print_stat(
+    out: &mut dyn Write,
+    files: &[FileStat],
+    hotspots: &[Hotspot],
+) -> io::Result<()> {
+    let width = files
+        .iter()
+        .map(|file| file.path.to_string_lossy().chars().count())
+        .max()
+        .unwrap_or(0);
+    let most = files.iter().map(FileStat::changes).max().unwrap_or(0);
+    let digits = most.to_string().len();
+    let bar = |lines: usize| match most > BAR_WIDTH {
+        true => (lines * BAR_WIDTH).div_ceil(most),
+        false => lines,
+    };
+
+    for file in files {
+        let path = format!("{:<width$}", file.path.to_string_lossy());
+        let path = match hotspots.iter().any(|hotspot| hotspot.path == file.path) {
+            true => path.red().bold(),
+            false => path.normal(),
+        };
+        match (file.insertions, file.deletions) {
+            (Some(insertions), Some(deletions)) => writeln!(
+                out,
+                " {path} | {:>digits$} {}{}",
+                file.changes(),
+                "+".repeat(bar(insertions)).green(),
+                "-".repeat(bar(deletions)).red()
+            )?,
+            _ => writeln!(out, " {path} | {:>digits$}", "Bin")?,
+        }
+    }
+    let insertions: usize = files.iter().filter_map(|file| file.insertions).sum();
+    let deletions: usize = files.iter().filter_map(|file| file.deletions).sum();
+    writeln!(
+        out,
+        " {} file{} changed, {insertions} insertion{}(+), {deletions} deletion{}(-)",
+        files.len(),
+        plural(files.len()),
+        plural(insertions),
+        plural(deletions)
+    )?;
+    writeln!(out)
+}
+
+/// The hotspots, one per line with the branches that changed them.
+pub(crate) fn print_hotspots(out: &mut dyn Write, hotspots: &[Hotspot]) -> io::Result<()> {
+    for hotspot in hotspots {
+        writeln!(
+            out,
+            "{} ({})",
+            hotspot.path.to_string_lossy().red().bold(),
+            hotspot.branches.join(", ")
+        )?;
+    }
+    writeln!(out)
+}
+
+/// `<fields>... <insertions> <deletions> <path>` records, e.g. `file <branch> ...`, with `-`
+/// counting lines of binary files.
+pub(crate) fn porcelain_files(
+    out: &mut dyn Write,
+    fields: &[&str],
+    files: &[FileStat],
+) -> io::Result<()> {
+    let count = |lines: Option<usize>| lines.map_or("-".to_string(), |lines| lines.to_string());
+    for file in files {
+        let (insertions, deletions) = (count(file.insertions), count(file.deletions));
+        let mut line: Vec<&OsStr> = fields.iter().map(OsStr::new).collect();
+        line.extend([
+            OsStr::new(&insertions),
+            OsStr::new(&deletions),
+            file.path.as_os_str(),
+        ]);
+        porcelain_line(out, &line)?;
+    }
+    Ok(())
+}
+
+/// `hotspot <path>` records.
+pub(crate) fn porcelain_hotspots(out: &mut dyn Write, hotspots: &[Hotspot]) -> io::Result<()> {
+    for hotspot in hotspots {
+        porcelain_line(out, &[OsStr::new("hotspot"), hotspot.path.as_os_str()])?;
+    }
+    Ok(())
+}
+
+fn plural(count: usize) -> &'static str {
+    match count {
+        1 => "",
+        _ => "s",
+    }
+}
@@ -0,0 +1,626 @@
+use git_test_fixture::TestRepo;
+
+#[test]
+fn shows_both_sides_and_merge_base() {
+    let fixture = TestRepo::new("sdif-cli");
+    let base = fixture.commit("Initial");
+    fixture.branch("dev").commit("Dev work");
+    fixture.checkout("master").commit("Master work");
+
+    let output = fixture.run(env!("CARGO_BIN_EXE_git-sdif"), &["master", "dev"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    let master = stdout.find("Master work").unwrap();
+    let dev = stdout.find("Dev work").unwrap();
+    assert!(master < dev);
+    assert!(stdout.contains(&format!("Found Merge base: {base}")));
+    assert!(stdout.contains(&format!("commit {base}")));
+}
+
+#[test]
+fn unrelated_branches() {
+    let fixture = TestRepo::new("sdif-cli-unrelated");
+    let master = fixture.commit_file("a.txt", "a\n", "Initial");
+    fixture.git(&["checkout", "--quiet", "--orphan", "other"]);
+    fixture.git(&["rm", "--quiet", "-rf", "."]);
+    let other = fixture.commit_file("b.txt", "b\n", "Other root");
+    let sdif = env!("CARGO_BIN_EXE_git-sdif");
+
+    let output = fixture.run(sdif, &["master", "other"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("No merge base: the histories are unrelated\n"));
+    assert!(stdout.contains(&format!("commit {other}")));
+    assert!(stdout.contains(" 2 files changed, 1 insertion(+), 1 deletion(-)\n"));
+
+    let output = fixture.run(sdif, &["--json", "master", "other"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json["merge_base"].is_null());
+    assert_eq!(json["merge_bases"].as_array().unwrap().len(), 0);
+    assert_eq!(json["sides"][0]["roots"][0], master);
+    assert_eq!(json["tree_diff"][0]["path"], "a.txt");
+
+    let output = fixture.run(sdif, &["--format=porcelain", "master", "other"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&format!("root\tother\t{other}\n")));
+    assert!(stdout.ends_with("diff\t0\t1\ta.txt\ndiff\t1\t0\tb.txt\n"));
+
+    // merged as if both started from nothing
+    let output = fixture.run(sdif, &["--conflicts", "master", "other"]);
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn criss_cross_merges() {
+    let fixture = TestRepo::new("sdif-cli-criss-cross");
+    fixture.commit("Initial");
+    let dev = fixture.branch("dev").commit("Dev work");
+    let master = fixture.checkout("master").commit("Master work");
+    fixture.git(&["merge", "--quiet", "--no-ff", "--no-edit", &dev]);
+    fixture.checkout("dev");
+    fixture.git(&["merge", "--quiet", "--no-ff", "--no-edit", &master]);
+    let sdif = env!("CARGO_BIN_EXE_git-sdif");
+
+    let output = fixture.run(sdif, &["--json", "master", "dev"]);
+    assert!(output.status.success(), "{output:?}");
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let mut merge_bases: Vec<&str> = json["merge_bases"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|base| base["hash"].as_str().unwrap())
+        .collect();
+    merge_bases.sort();
+    let mut expected = [dev.as_str(), master.as_str()];
+    expected.sort();
+    assert_eq!(merge_bases, expected);
+
+    let output = fixture.run(sdif, &["master", "dev"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("Found 2 merge bases (criss-cross merges): "));
+    assert!(stdout.contains("Common anchestors of master and dev (2)"));
+    assert!(stdout.contains(&format!("commit {dev}")));
+    assert!(stdout.contains(&format!("commit {master}")));
+}
+
+#[test]
+fn json_and_porcelain() {
+    let fixture = TestRepo::new("sdif-cli-format");
+    let base = fixture.commit("Initial");
+    let dev = fixture.branch("dev").commit("Dev work");
+    fixture.checkout("master");
+
+    let output = fixture.run(
+        env!("CARGO_BIN_EXE_git-sdif"),
+        &["--format=json", "master", "dev"],
+    );
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["merge_base"]["hash"], base);
+    assert_eq!(json["sides"][0]["commits"].as_array().unwrap().len(), 0);
+    assert_eq!(json["sides"][1]["name"], "dev");
+    assert_eq!(json["sides"][1]["commits"][0]["subject"], "Dev work");
+
+    let output = fixture.run(env!("CARGO_BIN_EXE_git-sdif"), &["--json", "master", "dev"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let side = &json["sides"][1];
+    assert_eq!(side["head"], dev);
+    assert_eq!(side["count"], 1);
+    assert_eq!(side["commits"][0]["hash"], dev);
+    assert_eq!(side["commits"][0]["parents"][0], base);
+    assert_eq!(side["commits"][0]["author"]["name"], "Test Author");
+    assert_eq!(side["commits"][0]["author"]["date"], "2022-04-15T05:21:00Z");
+    assert_eq!(json["sides"][0]["count"], 0);
+
+    let output = fixture.run(
+        env!("CARGO_BIN_EXE_git-sdif"),
+        &["--format", "porcelain", "master", "dev"],
+    );
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("merge-base\t{base}\nunique\tdev\t{dev}\tDev work\n")
+    );
+}
+
+#[test]
+fn trace_file() {
+    let fixture = TestRepo::new("sdif-cli-trace");
+    fixture.commit("Initial");
+    fixture.branch("dev").commit("Dev work");
+    let trace = fixture.path().join("trace.log");
+
+    let output = fixture
+        .command(env!("CARGO_BIN_EXE_git-sdif"))
+        .args(["--trace-file", "trace.log", "master", "dev"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("git config"));
+    // reading the excess.* settings always runs git, whatever the backend; none are set
+    let log = std::fs::read_to_string(&trace).unwrap();
+    assert!(log.contains("git config --null --show-scope"), "{log}");
+    assert!(log.contains("; exit 1; "), "{log}");
+
+    let output = fixture
+        .command(env!("CARGO_BIN_EXE_git-sdif"))
+        .args(["-v", "master", "dev"])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stderr).contains("git config --null --show-scope"));
+}
+
+#[cfg(unix)]
+#[test]
+fn latin1_commit_message() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let fixture = TestRepo::new("sdif-cli-latin1");
+    fixture.commit("Initial");
+    fixture.branch("dev").commit(OsStr::from_bytes(b"Caf\xe9"));
+
+    let output = fixture.run(env!("CARGO_BIN_EXE_git-sdif"), &["master", "dev"]);
+    assert!(output.status.success(), "{output:?}");
+    // git log reencodes the message as Latin-1, gitoxide replaces the invalid byte
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Café") || stdout.contains("Caf\u{fffd}"));
+}
+
+#[test]
+fn color() {
+    let fixture = TestRepo::new("sdif-cli-color");
+    fixture.commit("Initial");
+    fixture.branch("dev").commit("Dev work");
+    let sdif = env!("CARGO_BIN_EXE_git-sdif");
+    let colored = |cmd: &mut std::process::Command| {
+        let output = cmd.output().unwrap();
+        assert!(output.status.success());
+        output.stdout.contains(&0x1b)
+    };
+
+    // stdout is a pipe
+    assert!(!colored(fixture.command(sdif).args(["master", "dev"])));
+    assert!(colored(
+        fixture.command(sdif).args(["--color", "master", "dev"])
+    ));
+
+    fixture.git(&["config", "color.ui", "always"]);
+    assert!(colored(fixture.command(sdif).args(["master", "dev"])));
+    assert!(!colored(
+        fixture
+            .command(sdif)
+            .args(["master", "dev"])
+            .env("NO_COLOR", "1")
+    ));
+    assert!(!colored(fixture.command(sdif).args([
+        "--color=never",
+        "master",
+        "dev"
+    ])));
+}
+
+#[test]
+fn cherry_picked_commits() {
+    let fixture = TestRepo::new("sdif-cli-cherry");
+    fixture.commit_file("a.txt", "a\n", "Initial");
+    let fix = fixture.branch("dev").commit_file("fix.txt", "fix\n", "Fix");
+    let dev = fixture.commit_file("dev.txt", "dev\n", "Dev work");
+    fixture
+        .checkout("master")
+        .commit_file("master.txt", "master\n", "Master work");
+    fixture.git(&["cherry-pick", "--quiet", &fix]);
+    let picked = fixture.head();
+    let sdif = env!("CARGO_BIN_EXE_git-sdif");
+
+    let output = fixture.run(sdif, &["--json", "master", "dev"]);
+    assert!(output.status.success(), "{output:?}");
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["sides"][0]["count"], 2);
+    assert_eq!(json["sides"][0]["missing"], 1);
+    assert_eq!(json["sides"][0]["commits"][0]["equivalent"], fix);
+    assert_eq!(
+        json["sides"][1]["commits"][0]["equivalent"],
+        serde_json::Value::Null
+    );
+    assert_eq!(json["sides"][1]["commits"][1]["equivalent"], picked);
+    assert_eq!(json["equivalent"][0]["commits"][0], picked);
+    assert_eq!(json["equivalent"][0]["commits"][1], fix);
+
+    let output = fixture.run(sdif, &["--format=porcelain", "master", "dev"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&format!("unique\tdev\t{dev}\tDev work\n")));
+    assert!(!stdout.contains(&format!("unique\tdev\t{fix}")));
+    assert!(stdout.contains(&format!("equivalent\t{picked}\t{fix}\tFix\n")));
+
+    let output = fixture.run(sdif, &["master", "dev"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Commits unique to dev (1)"), "{stdout}");
+    assert!(stdout.contains("Cherry-picked between master and dev (1)"));
+    assert!(stdout.contains(&format!("{} = {} Fix", &picked[..7], &fix[..7])));
+}
+
+#[test]
+fn more_than_two_branches() {
+    let fixture = TestRepo::new("sdif-cli-matrix");
+    let base = fixture.commit("Initial");
+    let dev = fixture.branch("dev").commit("Dev work");
+    fixture.checkout("master").branch("release");
+    let release = fixture.commit("Release fix");
+    fixture.git(&["merge", "--quiet", "--no-edit", "dev"]);
+    let merge = fixture.head();
+    let master = fixture.checkout("master").commit("Master work");
+    let sdif = env!("CARGO_BIN_EXE_git-sdif");
+
+    let output = fixture.run(sdif, &["--json", "master", "release", "dev"]);
+    assert!(output.status.success(), "{output:?}");
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["merge_base"]["hash"], base);
+    assert_eq!(json["branches"][2]["name"], "dev");
+    assert_eq!(json["branches"][2]["head"], dev);
+    assert_eq!(json["commits"].as_array().unwrap().len(), 4);
+
+    let output = fixture.run(sdif, &["--format=porcelain", "master", "release", "dev"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with(&format!("merge-base\t{base}\n")));
+    assert!(stdout.contains(&format!("commit\t{master}\tmaster\tMaster work\n")));
+    assert!(stdout.contains(&format!("commit\t{merge}\trelease\t")));
+    assert!(stdout.contains(&format!("commit\t{release}\trelease\tRelease fix\n")));
+    assert!(stdout.contains(&format!("commit\t{dev}\trelease dev\tDev work\n")));
+
+    let output = fixture.run(sdif, &["master", "release", "dev"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("master  release  dev\n"), "{stdout}");
+    assert!(stdout.contains("-       *        *    "));
+}
+
+#[test]
+fn stat() {
+    let fixture = TestRepo::new("sdif-cli-stat");
+    fixture.commit_file("shared.txt", "a\n", "Initial");
+    fixture.branch("dev");
+    fixture.write("shared.txt", "1\n2\n3\n");
+    fixture.commit_file("dev.txt", "dev\n", "Dev work");
+    fixture.checkout("master");
+    fixture.commit_file("shared.txt", "b\n", "Master work");
+    let sdif = env!("CARGO_BIN_EXE_git-sdif");
+
+    let output = fixture.run(sdif, &["--stat", "--json", "master", "dev"]);
+    assert!(output.status.success(), "{output:?}");
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let dev = &json["sides"][1]["files"];
+    assert_eq!(dev.as_array().unwrap().len(), 2);
+    assert_eq!(dev[1]["path"], "shared.txt");
+    assert_eq!(dev[1]["insertions"], 3);
+    assert_eq!(dev[1]["deletions"], 1);
+    assert_eq!(json["hotspots"][0]["path"], "shared.txt");
+    assert_eq!(json["hotspots"][0]["branches"][1], "dev");
+
+    let output = fixture.run(sdif, &["--stat", "--format=porcelain", "master", "dev"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("file\tmaster\t1\t1\tshared.txt\n"));
+    assert!(stdout.contains("file\tdev\t1\t0\tdev.txt\n"));
+    assert!(stdout.ends_with("hotspot\tshared.txt\n"));
+
+    let output = fixture.run(sdif, &["--stat", "master", "dev"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(" shared.txt | 4 +++-\n"), "{stdout}");
+    assert!(stdout.contains(" 2 files changed, 4 insertions(+), 1 deletion(-)\n"));
+    assert!(stdout.contains("Changed on both branches (1)"));
+
+    // without --stat there are no files at all
+    let output = fixture.run(sdif, &["--json", "master", "dev"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json["sides"][0].get("files").is_none());
+    assert!(json.get("hotspots").is_none());
+}
+
+#[test]
+fn conflicts() {
+    let fixture = TestRepo::new("sdif-cli-conflicts");
+    fixture.write("moved.txt", "moved\n");
+    fixture.commit_file("shared.txt", "a\n", "Initial");
+    fixture.branch("dev");
+    fixture.git(&["mv", "moved.txt", "renamed.txt"]);
+    fixture.write("added.txt", "dev\n");
+    fixture.commit_file("shared.txt", "dev\n", "Dev work");
+    fixture.checkout("master").branch("clean");
+    fixture.commit_file("clean.txt", "clean\n", "Clean work");
+    fixture.checkout("master");
+    fixture.git(&["rm", "--quiet", "moved.txt"]);
+    fixture.write("added.txt", "master\n");
+    fixture.commit_file("shared.txt", "master\n", "Master work");
+    let head = fixture.head();
+    let sdif = env!("CARGO_BIN_EXE_git-sdif");
+
+    let output = fixture.run(sdif, &["--conflicts", "--json", "master", "dev"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["clean"], false);
+    let kinds: Vec<&str> = json["conflicts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|conflict| conflict["kind"].as_str().unwrap())
+        .collect();
+    assert!(kinds.contains(&"rename/delete"), "{kinds:?}");
+    assert_eq!(kinds.iter().filter(|kind| **kind == "content").count(), 1);
+    assert!(kinds.contains(&"add/add"), "{kinds:?}");
+
+    let output = fixture.run(
+        sdif,
+        &["--conflicts", "--format=porcelain", "master", "dev"],
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("conflict\tcontent\tshared.txt\n"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("conflict\trename/delete\trenamed.txt\tmoved.txt\n"));
+
+    let output = fixture.run(sdif, &["--conflicts", "master", "clean"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "clean merges cleanly into master\n"
+    );
+
+    // nothing was merged for real
+    assert_eq!(fixture.head(), head);
+    assert!(fixture.git(&["status", "--porcelain"]).is_empty());
+    let output = fixture.run(sdif, &["--conflicts", "master", "dev", "clean"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn add_add_conflict() {
+    let fixture = TestRepo::new("sdif-cli-add-add");
+    fixture.commit("Initial");
+    fixture.branch("dev");
+    fixture.commit_file("new.txt", "dev\n", "Add new on dev");
+    fixture.checkout("master");
+    fixture.commit_file("new.txt", "master\n", "Add new on master");
+
+    let output = fixture.run(
+        env!("CARGO_BIN_EXE_git-sdif"),
+        &["--conflicts", "--format=porcelain", "master", "dev"],
+    );
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("conflict\tadd/add\tnew.txt\n"), "{stdout}");
+}
+
+#[test]
+fn all_branches() {
+    let fixture = TestRepo::new("sdif-cli-all");
+    fixture.commit("Initial");
+    let merged = fixture.branch("merged").head();
+    let dev = fixture.branch("dev").commit("Dev work");
+    fixture.git(&["branch", "--quiet", "--set-upstream-to=master"]);
+    fixture.checkout("master").commit("Master work");
+    fixture.git(&["remote", "add", "origin", "https://example.com/repo.git"]);
+    fixture.git(&["config", "branch.merged.remote", "origin"]);
+    fixture.git(&["config", "branch.merged.merge", "refs/heads/merged"]);
+    let sdif = env!("CARGO_BIN_EXE_git-sdif");
+
+    // the fixture's commits are years old
+    let output = fixture.run(sdif, &["--all", "--format=porcelain"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            format!("branch\tdev\t{dev}\tmaster\t1\t1\t1\t1\tstale"),
+            format!("branch\tmaster\t{}\t-\t-\t-\t0\t0\tdefault", fixture.head()),
+            format!("branch\tmerged\t{merged}\torigin/merged\t-\t-\t0\t1\tmerged"),
+        ]
+    );
+
+    fixture.git(&["config", "excess.sdif.staleDays", "1000000"]);
+    let output = fixture.run(sdif, &["--all", "--json", "--sort=behind"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["default_branch"], "master");
+    assert_eq!(json["stale_days"], 1000000);
+    let dev = &json["branches"][0];
+    assert_eq!(dev["name"], "dev");
+    assert_eq!(dev["status"], "diverged");
+    assert_eq!(dev["upstream"]["ahead"], 1);
+    assert_eq!(json["branches"][1]["upstream"]["gone"], true);
+
+    let output = fixture.run(sdif, &["--all", "--default-branch=dev"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.starts_with("Branch  Last commit  Upstream"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("dev +/-"));
+    let output = fixture.run(sdif, &["--all", "dev"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn filters() {
+    let fixture = TestRepo::new("sdif-cli-filters");
+    fixture.commit("Initial");
+    fixture.branch("dev");
+    fixture.commit_file("billing/invoice.txt", "1\n", "Bill twice");
+    fixture.commit_file("docs/billing.md", "1\n", "Document billing");
+    fixture.git(&[
+        "commit",
+        "--quiet",
+        "--allow-empty",
+        "--author=Ann <ann@example.com>",
+        "-m",
+        "Ann's fix",
+    ]);
+    fixture.branch("topic").commit("Topic work");
+    fixture.checkout("dev");
+    fixture.git(&["merge", "--quiet", "--no-ff", "--no-edit", "topic"]);
+    fixture.checkout("master");
+    let sdif = env!("CARGO_BIN_EXE_git-sdif");
+    let unique = |args: &[&str]| -> Vec<String> {
+        let args = [&["--format=porcelain", "master", "dev"], args].concat();
+        let output = fixture.run(sdif, &args);
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.strip_prefix("unique\tdev\t"))
+            .map(|line| line.split_once('\t').unwrap().1.to_string())
+            .collect()
+    };
+
+    assert_eq!(unique(&[]).len(), 5);
+    assert_eq!(unique(&["--", "billing"]), ["Bill twice"]);
+    assert_eq!(unique(&["--author=ann@"]), ["Ann's fix"]);
+    assert_eq!(unique(&["--grep=^Doc"]), ["Document billing"]);
+    assert_eq!(unique(&["--no-merges"]).len(), 4);
+    assert!(unique(&["--since=2100-01-01"]).is_empty());
+    assert_eq!(
+        unique(&["--grep=billing", "--", "docs"]),
+        ["Document billing"]
+    );
+
+    let output = fixture.run(sdif, &["--json", "--grep=Bill", "master", "dev"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["sides"][1]["count"], 1);
+    assert_eq!(json["sides"][1]["missing"], 1);
+
+    let output = fixture.run(sdif, &["--all", "--no-merges"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn range_diff() {
+    let fixture = TestRepo::new("sdif-cli-range-diff");
+    fixture.commit("Initial");
+    fixture.branch("feature");
+    let add = fixture.commit_file("a.txt", "a\n", "Add a");
+    let edit = fixture.commit_file("b.txt", "1\n", "Edit b\n\nChange-Id: I12ab");
+    let docs = fixture.commit_file("docs.md", "old\n", "Tweak docs");
+    let dropped = fixture.commit_file("drop.txt", "x\n", "Drop me");
+    fixture
+        .checkout("master")
+        .commit_file("base.txt", "base\n", "Upstream");
+    // rebased by hand onto master, rewriting two of the commits
+    fixture.branch("rebased");
+    let add2 = fixture.commit_file("a.txt", "a\n", "Add a");
+    let edit2 = fixture.commit_file("b.txt", "2\n", "Edit b better\n\nChange-Id: I12ab");
+    let docs2 = fixture.commit_file("docs.md", "new\n", "Tweak docs");
+    fixture.commit_file("new.txt", "n\n", "New work");
+    let sdif = env!("CARGO_BIN_EXE_git-sdif");
+
+    // without --range-diff only the identical change is paired
+    let output = fixture.run(sdif, &["--json", "feature", "rebased"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["sides"][0]["missing"], 3);
+    assert_eq!(json["equivalent"].as_array().unwrap().len(), 1);
+
+    let output = fixture.run(sdif, &["--json", "--range-diff", "feature", "rebased"]);
+    assert!(output.status.success(), "{output:?}");
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["sides"][0]["missing"], 1);
+    assert_eq!(json["sides"][1]["missing"], 2);
+    let pairs = json["equivalent"].as_array().unwrap();
+    assert_eq!(pairs.len(), 3);
+    assert_eq!(pairs[1]["matched_by"], "change-id");
+    assert_eq!(
+        pairs[1]["changes"],
+        serde_json::json!(["content", "message"])
+    );
+    assert!(pairs[1]["patch_id"].is_null());
+
+    let output = fixture.run(
+        sdif,
+        &["--format=porcelain", "--range-diff", "feature", "rebased"],
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&format!("equivalent\t{add}\t{add2}\tAdd a\n")));
+    assert!(stdout.contains(&format!(
+        "rewritten\t{edit}\t{edit2}\tchange-id\tcontent,message\tEdit b\n"
+    )));
+    assert!(stdout.contains(&format!(
+        "rewritten\t{docs}\t{docs2}\tsubject\tcontent\tTweak docs\n"
+    )));
+
+    let output = fixture.run(sdif, &["--range-diff", "feature", "rebased"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let short = |hash: &str| hash[..7].to_string();
+    assert!(stdout.contains(&format!(
+        "1:  {} = 2:  {} Add a\n",
+        short(&add),
+        short(&add2)
+    )));
+    assert!(stdout.contains(&format!(
+        "2:  {} ! 3:  {} Edit b (change-id; content, message changed)\n",
+        short(&edit),
+        short(&edit2)
+    )));
+    assert!(stdout.contains(&format!("4:  {} < -:  ------- Drop me\n", short(&dropped))));
+    assert!(stdout.contains("-:  ------- > 5:  "));
+
+    let output = fixture.run(sdif, &["--range-diff", "feature", "rebased", "master"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn release_notes() {
+    let fixture = TestRepo::new("sdif-cli-release-notes");
+    fixture.commit("Initial");
+    fixture.branch("dev");
+    let sso = fixture.commit("feat(auth): support SSO\n\nRefs ABC-12");
+    fixture.commit("fix: crash on empty input\n\nFixes ABC-9");
+    fixture.commit("feat!: drop the v1 API");
+    fixture.git(&[
+        "commit",
+        "--quiet",
+        "--allow-empty",
+        "--author=Ann <ann@example.com>",
+        "-m",
+        "Update the README",
+    ]);
+    fixture.checkout("master");
+    let sdif = env!("CARGO_BIN_EXE_git-sdif");
+
+    let output = fixture.run(sdif, &["--release-notes", "dev", "master"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let expected = format!(
+        "# Release notes
+
+Changes in `dev` not in `master` (4 commits).
+
+## Breaking Changes
+
+- drop the v1 API ({}, Test Author)
+
+## Features
+
+- **auth:** support SSO ({}, Test Author)
+",
+        fixture.git(&["rev-parse", "--short=7", "dev~1"]).trim(),
+        &sso[..7]
+    );
+    assert!(stdout.starts_with(&expected), "{stdout}");
+    assert!(stdout.contains("## Bug Fixes\n\n- crash on empty input"));
+    assert!(stdout.contains("## Other Changes\n\n- Update the README"));
+    assert!(stdout.ends_with("## Authors\n\n- Test Author (3)\n- Ann (1)\n"));
+
+    let output = fixture.run(sdif, &["--release-notes=issue", "--json", "dev", "master"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let titles: Vec<&str> = json["sections"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|section| section["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, ["ABC-9", "ABC-12", "Other Changes"]);
+    assert_eq!(json["sections"][1]["notes"][0]["scope"], "auth");
+    assert_eq!(json["authors"][1]["email"], "ann@example.com");
+
+    let output = fixture.run(sdif, &["--release-notes", "--format=porcelain", "dev"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&format!("note\tFeatures\t{sso}\tfeat(auth): support SSO\n")));
+
+    let output = fixture.run(sdif, &["--release-notes", "--stat", "dev", "master"]);
+    assert_eq!(output.status.code(), Some(2));
+}
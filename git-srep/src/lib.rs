@@ -0,0 +1,298 @@
+use clap::{CommandFactory, Parser};
+use colored::Colorize;
+use git_utils_shared::{
+    cli::GlobalArgs,
+    complete,
+    report::{self, lossy_path, porcelain_line, Report},
+    settings::flag,
+    GitError, Repo,
+};
+use serde::Serialize;
+use std::{
+    ffi::{OsStr, OsString},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// Replace given phrase inside files of the provided paths.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// The word to substitute
+    search: String,
+    /// The word to substitute with
+    replace: String,
+
+    #[clap(
+        long,
+        short,
+        help = "Replace words even if the matched file is modified (file not staged)"
+    )]
+    force: bool,
+
+    #[clap(
+        long,
+        overrides_with = "force",
+        help = "Refuse to replace in modified files, even if excess.srep.force is set"
+    )]
+    no_force: bool,
+
+    #[clap(
+        long,
+        help = "Don't execute replacement, instead print replacement command"
+    )]
+    dry_run: bool,
+
+    #[clap(
+        long,
+        overrides_with = "dry-run",
+        help = "Replace even if excess.srep.dryRun is set"
+    )]
+    no_dry_run: bool,
+
+    #[clap(flatten)]
+    global: GlobalArgs,
+
+    #[clap(default_value = ".", parse(from_os_str))]
+    paths: Vec<PathBuf>,
+}
+
+fn change_word_in_files(
+    repo: &Repo,
+    file_path: &[PathBuf],
+    search: &str,
+    replace: &str,
+) -> Result<(), GitError> {
+    let mut sed = repo.command("sed");
+    sed.args([
+        "-i",
+        "-E",
+        &format!(
+            "s/{}/{}/g",
+            search.replace('/', r"\/"),
+            replace.replace('/', r"\/")
+        ),
+    ])
+    // files may start with a dash
+    .arg("--")
+    .args(file_path);
+    repo.run_status(&mut sed)?;
+    Ok(())
+}
+
+/// What happened to the files containing the search word.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+enum Outcome {
+    /// The word was replaced.
+    Replaced,
+    /// `--dry-run`: the word would have been replaced.
+    DryRun,
+    /// Nothing was replaced because some files have unstaged changes.
+    Refused,
+    /// No file contains the word.
+    NoMatch,
+}
+
+#[derive(Serialize)]
+struct FileChange {
+    #[serde(serialize_with = "lossy_path")]
+    path: PathBuf,
+    /// The file had unstaged changes before the replacement.
+    unstaged: bool,
+}
+
+#[derive(Serialize)]
+struct Replacement {
+    search: String,
+    replace: String,
+    outcome: Outcome,
+    files: Vec<FileChange>,
+}
+
+impl Report for Replacement {
+    fn human(&self, out: &mut dyn Write) -> io::Result<()> {
+        match self.outcome {
+            Outcome::Refused => {
+                let unstaged: Vec<String> = self
+                    .files
+                    .iter()
+                    .filter(|file| file.unstaged)
+                    .map(|file| file.path.display().to_string())
+                    .collect();
+                writeln!(
+                    out,
+                    "In the matched files, there are unstaged changes: \n- {} ",
+                    unstaged.join("\n- ")
+                )?;
+                writeln!(
+                    out,
+                    "{}: stage all changes of use flag -f to force replacement.",
+                    "hint".bold()
+                )
+            }
+            Outcome::DryRun => {
+                let files: Vec<String> = self
+                    .files
+                    .iter()
+                    .map(|file| file.path.display().to_string())
+                    .collect();
+                writeln!(
+                    out,
+                    "Found \"{}\" in : \n- {}",
+                    &self.search.cyan(),
+                    files.join("\n- ")
+                )?;
+                writeln!(
+                    out,
+                    "Did not perform replacement due to {}",
+                    "--dry-run".bold().bright_yellow()
+                )
+            }
+            Outcome::Replaced => {
+                writeln!(
+                    out,
+                    "{} \"{}\" -> \"{}\" in :",
+                    "Succesfully changed".bold().green(),
+                    &self.search.cyan(),
+                    &self.replace.cyan(),
+                )?;
+                for file in &self.files {
+                    if file.unstaged {
+                        writeln!(out, "- {} (had unstaged content)", file.path.display())?;
+                    } else {
+                        writeln!(out, "- {}", file.path.display())?;
+                    }
+                }
+                Ok(())
+            }
+            Outcome::NoMatch => writeln!(
+                out,
+                "There is not any file containing the word \"{}\".",
+                &self.search.cyan()
+            ),
+        }
+    }
+
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()> {
+        let outcome = match self.outcome {
+            Outcome::Replaced => "replaced",
+            Outcome::DryRun => "dry-run",
+            Outcome::Refused => "refused",
+            Outcome::NoMatch => "no-match",
+        };
+        porcelain_line(out, &[outcome])?;
+        for file in &self.files {
+            let state = if file.unstaged { "unstaged" } else { "clean" };
+            porcelain_line(
+                out,
+                &[OsStr::new("file"), OsStr::new(state), file.path.as_os_str()],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The clap definition of git-srep, for completions and man pages.
+pub fn command() -> clap::Command<'static> {
+    Args::command()
+}
+
+/// Tracked paths for the pathspecs to replace in.
+pub fn complete_value(_path: &[&str], arg: &str, prefix: &str) -> Vec<String> {
+    match arg {
+        "paths" => Repo::current()
+            .and_then(|repo| repo.tracked_files())
+            .map(|files| complete::path_candidates(&files, prefix))
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+/// Run git-srep with the command line `args`, the first one being the program name.
+///
+/// Exits the process when the tool fails; the multicall `git-excess` binary calls this too.
+pub fn main<I, T>(args: I)
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    complete::intercept(&args, command, complete_value);
+    let args = Args::parse_from(args);
+
+    match run(&args) {
+        // nothing to replace and dry runs exit with 1, like before the report existed
+        Ok(Outcome::NoMatch | Outcome::DryRun) => std::process::exit(1),
+        Ok(_) => {}
+        Err(err) => {
+            eprintln!("{} {}", "error:".red().bold(), err);
+            std::process::exit(err.exit_code());
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<Outcome, GitError> {
+    let repo = Repo::current()?;
+    args.global.init(&repo)?;
+    let settings = repo.settings()?;
+    let format = settings.value("excess.srep.format", args.global.format)?;
+    let force = settings.bool("excess.srep.force", flag(args.force, args.no_force))?;
+    let dry_run = settings.bool("excess.srep.dryRun", flag(args.dry_run, args.no_dry_run))?;
+    let files = repo
+        .get_files_with_word(&args.search, &args.paths)?
+        .unwrap_or_default();
+    // without paths git diff would list every modified file
+    let modified_files = match files.is_empty() {
+        true => Vec::new(),
+        false => repo.modified_files(&files)?.unwrap_or_default(),
+    };
+
+    let outcome = if files.is_empty() {
+        Outcome::NoMatch
+    } else if !modified_files.is_empty() && !force {
+        Outcome::Refused
+    } else if dry_run {
+        Outcome::DryRun
+    } else {
+        change_word_in_files(&repo, &files, &args.search, &args.replace)?;
+        Outcome::Replaced
+    };
+
+    let files = files
+        .into_iter()
+        .map(|path| FileChange {
+            unstaged: modified_files.contains(&path),
+            path,
+        })
+        .filter(|file| outcome != Outcome::Refused || file.unstaged)
+        .collect();
+    report::print(
+        format,
+        &Replacement {
+            search: args.search.clone(),
+            replace: args.replace.clone(),
+            outcome,
+            files,
+        },
+    )?;
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git_utils_shared::runner::MockRunner;
+    use std::sync::Arc;
+
+    #[test]
+    fn sed_failure() {
+        let mock = MockRunner::new()
+            .expect(&["sed", "-i", "-E", r"s/a\/b/c/g", "--", "-x.txt"])
+            .exits(4);
+        let repo = Repo::new(".").with_runner(Arc::new(mock));
+
+        let err = change_word_in_files(&repo, &[PathBuf::from("-x.txt")], "a/b", "c").unwrap_err();
+        assert_eq!(err.exit_code(), 4);
+        assert!(err.to_string().contains("sed -i -E"), "{err}");
+    }
+}
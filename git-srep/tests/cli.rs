@@ -0,0 +1,126 @@
+use git_test_fixture::TestRepo;
+
+const SREP: &str = env!("CARGO_BIN_EXE_git-srep");
+
+#[test]
+fn with_git() {
+    let fixture = TestRepo::new("srep");
+    fixture.write(r"subfolder/b\n.txt", "test_a");
+    fixture.git(&["add", "--all"]);
+    fixture.write("a.txt", "test_a");
+
+    let output = fixture.run(SREP, &["test_a", "new_test_a", "subfolder"]);
+    assert!(output.status.success());
+    assert_eq!(fixture.read(r"subfolder/b\n.txt"), "new_test_a");
+    assert_eq!(fixture.read("a.txt"), "test_a");
+
+    fixture.git(&["add", "--all"]);
+    let output = fixture.run(SREP, &["test_a", "new_test_a", "."]);
+    assert!(output.status.success());
+    assert_eq!(fixture.read("a.txt"), "new_test_a");
+}
+
+#[test]
+fn refuses_unstaged_changes() {
+    let fixture = TestRepo::new("srep-unstaged");
+    fixture.commit_file("a.txt", "old", "Add a");
+    fixture.write("a.txt", "old and changed");
+
+    fixture.run(SREP, &["old", "new"]);
+    assert_eq!(fixture.read("a.txt"), "old and changed");
+
+    let output = fixture.run(SREP, &["--force", "old", "new"]);
+    assert!(output.status.success());
+    assert_eq!(fixture.read("a.txt"), "new and changed");
+}
+
+#[test]
+fn from_subdirectory() {
+    let fixture = TestRepo::new("srep-subdirectory");
+    fixture.commit_file("sub/a.txt", "old", "Add a");
+    fixture.commit_file("b.txt", "old", "Add b");
+    fixture.write("sub/a.txt", "old and changed");
+    fixture.write("b.txt", "old and changed");
+    let run = |args: &[&str]| {
+        let output = fixture
+            .command(SREP)
+            .current_dir(fixture.path().join("sub"))
+            .args(args)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert_eq!(
+        run(&["--format=porcelain", "old", "new", ".."]),
+        "refused\nfile\tunstaged\t../b.txt\nfile\tunstaged\ta.txt\n"
+    );
+    assert!(run(&["old", "new"]).contains("- a.txt"));
+    assert_eq!(fixture.read("sub/a.txt"), "old and changed");
+
+    let output = run(&["--force", "old", "new", ".."]);
+    assert!(output.contains("- ../b.txt (had unstaged content)"));
+    assert!(output.contains("- a.txt (had unstaged content)"));
+    assert_eq!(fixture.read("sub/a.txt"), "new and changed");
+    assert_eq!(fixture.read("b.txt"), "new and changed");
+}
+
+#[test]
+fn porcelain() {
+    let fixture = TestRepo::new("srep-porcelain");
+    fixture.commit_file("a.txt", "old", "Add a");
+
+    let output = fixture.run(SREP, &["--format=porcelain", "--dry-run", "old", "new"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "dry-run\nfile\tclean\ta.txt\n"
+    );
+    assert_eq!(fixture.read("a.txt"), "old");
+}
+
+#[test]
+fn defaults_from_git_config() {
+    let fixture = TestRepo::new("srep-config");
+    fixture.commit_file("a.txt", "old", "Add a");
+    fixture.git(&["config", "excess.srep.dryRun", "true"]);
+
+    let output = fixture.run(SREP, &["old", "new"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(fixture.read("a.txt"), "old");
+
+    let output = fixture.run(SREP, &["--no-dry-run", "old", "new"]);
+    assert!(output.status.success());
+    assert_eq!(fixture.read("a.txt"), "new");
+
+    fixture.git(&["config", "excess.srep.dryRun", "maybe"]);
+    let output = fixture.run(SREP, &["new", "old"]);
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("bad value `maybe` for excess.srep.dryrun (local file:.git/config)"));
+}
+
+#[cfg(unix)]
+#[test]
+fn unusual_file_names() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let fixture = TestRepo::new("srep-names");
+    let latin1 = OsStr::from_bytes(b"caf\xe9.txt");
+    fixture.write("new\nline.txt", "old");
+    fixture.write("-dash.txt", "old");
+    fixture.write(latin1, "old");
+    fixture.commit("Add files");
+
+    let output = fixture.run(SREP, &["--format=porcelain", "--dry-run", "old", "new"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "dry-run\nfile\tclean\t-dash.txt\nfile\tclean\t\"caf\\351.txt\"\nfile\tclean\t\"new\\nline.txt\"\n"
+    );
+
+    let output = fixture.run(SREP, &["old", "new"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("- caf\u{fffd}.txt"));
+    assert_eq!(fixture.read("new\nline.txt"), "new");
+    assert_eq!(fixture.read("-dash.txt"), "new");
+    assert_eq!(fixture.read(latin1), "new");
+}
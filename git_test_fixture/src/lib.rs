@@ -0,0 +1,224 @@
+//! Temporary git repositories for the tests of the workspace tools.
+//!
+//! Every [`TestRepo`] lives in its own directory under the system temp dir, ignores the user's
+//! and system git config, and commits with a fixed identity and a clock that advances one minute
+//! per commit, so tests can run in parallel and get the same hashes on every run.
+use std::{
+    cell::Cell,
+    env,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use git_utils_shared::{trace, Repo};
+
+pub const AUTHOR_NAME: &str = "Test Author";
+pub const AUTHOR_EMAIL: &str = "author@example.com";
+/// Date of the first commit, later commits are one minute apart.
+pub const EPOCH: i64 = 1_650_000_000;
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A repository in a temporary directory, removed again when dropped.
+pub struct TestRepo {
+    repo: Repo,
+    /// Whether this value owns its directory, false for embedded repositories.
+    owned: bool,
+    commits: Cell<i64>,
+}
+
+impl TestRepo {
+    /// Initialise an empty repository on `master`; `name` only helps to find the directory.
+    pub fn new(name: &str) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let dir = env::temp_dir().join(format!("git-excess-{name}-{}-{id}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let test_repo = TestRepo {
+            repo: isolated(Repo::new(dir.canonicalize().unwrap())),
+            owned: true,
+            commits: Cell::new(0),
+        };
+        test_repo.git(&["init", "--quiet", "--initial-branch=master"]);
+        test_repo
+    }
+
+    /// Initialise an embedded repository at `path`, with `path/.egit` as its git dir.
+    pub fn embedded(&self, path: &str) -> TestRepo {
+        fs::create_dir_all(self.path().join(path)).unwrap();
+        let embedded = TestRepo {
+            repo: self.repo.embed(path),
+            owned: false,
+            commits: Cell::new(self.commits.get()),
+        };
+        embedded.git(&["init", "--quiet", "--initial-branch=master"]);
+        embedded
+    }
+
+    pub fn path(&self) -> &Path {
+        self.repo.work_tree()
+    }
+
+    /// The repository as seen by `git_utils_shared`, with the fixture environment.
+    pub fn repo(&self) -> &Repo {
+        &self.repo
+    }
+
+    /// Run git with `args`, panicking if it fails, and return its stdout.
+    pub fn git(&self, args: &[&str]) -> String {
+        let mut cmd = self.repo.git();
+        cmd.args(args);
+        stdout(&mut cmd)
+    }
+
+    /// Write `content` to `path` inside the work tree, creating parent directories.
+    pub fn write<P: AsRef<Path>>(&self, path: P, content: &str) -> &Self {
+        let file = self.path().join(path);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, content).unwrap();
+        self
+    }
+
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> String {
+        fs::read_to_string(self.path().join(path)).unwrap()
+    }
+
+    /// Stage everything and commit it, returning the new commit hash.
+    pub fn commit<M: AsRef<OsStr>>(&self, message: M) -> String {
+        self.git(&["add", "--all"]);
+        let date = format!("@{} +0000", self.tick());
+        let mut cmd = self.repo.git();
+        cmd.args(["commit", "--quiet", "--allow-empty", "-m"])
+            .arg(message)
+            .env("GIT_AUTHOR_DATE", &date)
+            .env("GIT_COMMITTER_DATE", &date);
+        stdout(&mut cmd);
+        self.head()
+    }
+
+    /// Write a file and commit it.
+    pub fn commit_file(&self, path: &str, content: &str, message: &str) -> String {
+        self.write(path, content).commit(message)
+    }
+
+    pub fn head(&self) -> String {
+        self.git(&["rev-parse", "HEAD"]).trim().to_string()
+    }
+
+    /// Create `branch` at the current commit and switch to it.
+    pub fn branch(&self, branch: &str) -> &Self {
+        self.git(&["checkout", "--quiet", "-b", branch]);
+        self
+    }
+
+    pub fn checkout(&self, rev: &str) -> &Self {
+        self.git(&["checkout", "--quiet", rev]);
+        self
+    }
+
+    /// Record an embedded project in `.gitembed` like `git embed add` does.
+    pub fn gitembed(&self, path: &str, url: &str, head: &str) -> &Self {
+        self.repo.add_fild_to_embed_file(path, "url", url).unwrap();
+        self.repo
+            .add_fild_to_embed_file(path, "path", path)
+            .unwrap();
+        self.repo
+            .add_fild_to_embed_file(path, "head", head)
+            .unwrap();
+        self
+    }
+
+    /// Build a command running `program` in the work tree with the fixture environment.
+    ///
+    /// The directory holding the workspace binaries comes first on `PATH`, so tools that call
+    /// each other (git-embed runs egit) find the freshly built versions.
+    pub fn command<S: AsRef<OsStr>>(&self, program: S) -> Command {
+        let mut cmd = self.repo.command(program);
+        let path = env::var_os("PATH").unwrap_or_default();
+        let paths = std::iter::once(bin_dir()).chain(env::split_paths(&path));
+        cmd.env("PATH", env::join_paths(paths).unwrap());
+        // binaries find the repository themselves, like they do for a user
+        cmd.env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .env_remove(trace::VERBOSE_ENV)
+            .env_remove(trace::TRACE_FILE_ENV)
+            .env_remove("NO_COLOR");
+        cmd
+    }
+
+    /// Run `program` with `args` in the work tree and return its output, whatever the status.
+    pub fn run<S: AsRef<OsStr>>(&self, program: S, args: &[&str]) -> Output {
+        self.command(program).args(args).output().unwrap()
+    }
+
+    fn tick(&self) -> i64 {
+        let commits = self.commits.get();
+        self.commits.set(commits + 1);
+        EPOCH + 60 * commits
+    }
+}
+
+impl Drop for TestRepo {
+    fn drop(&mut self) {
+        if self.owned {
+            let _ = fs::remove_dir_all(self.path());
+        }
+    }
+}
+
+/// Directory cargo puts the workspace binaries in, found from the running test executable.
+pub fn bin_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    let dir = exe.parent().unwrap();
+    if dir.ends_with("deps") {
+        dir.parent().unwrap().to_path_buf()
+    } else {
+        dir.to_path_buf()
+    }
+}
+
+/// Path of the workspace binary `name`, which must have been built already.
+pub fn bin(name: &str) -> PathBuf {
+    let path = bin_dir().join(format!("{name}{}", env::consts::EXE_SUFFIX));
+    assert!(
+        path.is_file(),
+        "{} not found, build it first with `cargo build -p {name}`",
+        path.display()
+    );
+    path
+}
+
+/// Keep the user's and system config out and use the fixture identity.
+fn isolated(repo: Repo) -> Repo {
+    repo.with_env("GIT_CONFIG_NOSYSTEM", "1")
+        .with_env("GIT_CONFIG_GLOBAL", null_device())
+        .with_env("GIT_AUTHOR_NAME", AUTHOR_NAME)
+        .with_env("GIT_AUTHOR_EMAIL", AUTHOR_EMAIL)
+        .with_env("GIT_COMMITTER_NAME", AUTHOR_NAME)
+        .with_env("GIT_COMMITTER_EMAIL", AUTHOR_EMAIL)
+        .with_env("GIT_AUTHOR_DATE", format!("@{EPOCH} +0000"))
+        .with_env("GIT_COMMITTER_DATE", format!("@{EPOCH} +0000"))
+        .with_env("NO_COLOR", "1")
+}
+
+fn null_device() -> &'static str {
+    if cfg!(windows) {
+        "NUL"
+    } else {
+        "/dev/null"
+    }
+}
+
+fn stdout(cmd: &mut Command) -> String {
+    let output = cmd.output().unwrap();
+    assert!(
+        output.status.success(),
+        "{cmd:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
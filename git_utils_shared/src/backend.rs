@@ -0,0 +1,538 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::Arc,
+};
+
+use crate::{
+    diff::{parse_numstat, FileStat},
+    into_string,
+    log::{parse_log, Commit, LogFilter, LOG_FORMAT},
+    merge::{parse_merge_tree, MergeTree},
+    path_from_bytes,
+    settings::{Scope, Setting},
+    split_nul, split_nul_paths, GitError, Repo,
+};
+
+#[cfg(feature = "gitoxide")]
+mod gitoxide;
+#[cfg(feature = "gitoxide")]
+pub use gitoxide::GitoxideBackend;
+
+/// The git operations the tools are built on.
+///
+/// [`Subprocess`] runs the `git` executable for each call. With the `gitoxide` feature,
+/// `GitoxideBackend` answers the read-only operations, rev-parse, merge-base, log, grep, ls-files,
+/// diff --name-only and the ref listings among them, in-process instead.
+pub trait Backend: fmt::Debug + Send + Sync {
+    /// `git rev-parse <rev>`
+    fn rev_parse(&self, repo: &Repo, rev: &str) -> Result<String, GitError>;
+
+    /// `git rev-parse --show-toplevel`
+    fn top_level_dir(&self, repo: &Repo) -> Result<PathBuf, GitError>;
+
+    /// `git merge-base <one> <two>`, `None` when the histories are unrelated.
+    fn merge_base(&self, repo: &Repo, one: &str, two: &str) -> Result<Option<String>, GitError>;
+
+    /// `git merge-base --all <revs>`, with `--octopus` for more than two: every best common
+    /// ancestor, several after criss-cross merges and none when the histories are unrelated.
+    fn merge_bases(&self, repo: &Repo, revs: &[&str]) -> Result<Vec<String>, GitError>;
+
+    /// Commits reachable from `revs` but not from `hidden`, newest first.
+    fn log(
+        &self,
+        repo: &Repo,
+        revs: &[&str],
+        hidden: &[&str],
+        max_count: Option<usize>,
+        filter: &LogFilter,
+    ) -> Result<Vec<Commit>, GitError>;
+
+    /// Number of commits [`Backend::log`] would list (`git rev-list --count`).
+    fn count(
+        &self,
+        repo: &Repo,
+        revs: &[&str],
+        hidden: &[&str],
+        filter: &LogFilter,
+    ) -> Result<usize, GitError>;
+
+    /// Stable patch IDs of the commits [`Backend::log`] lists, keyed by commit hash
+    /// (`git log -p | git patch-id --stable`). Merges and empty commits have none.
+    fn patch_ids(
+        &self,
+        repo: &Repo,
+        revs: &[&str],
+        hidden: &[&str],
+    ) -> Result<HashMap<String, String>, GitError>;
+
+    /// `git diff --numstat <from> <to>`, without rename detection.
+    fn diff_stat(&self, repo: &Repo, from: &str, to: &str) -> Result<Vec<FileStat>, GitError>;
+
+    /// `git merge-tree --write-tree <one> <two>`: merge them without a work tree or index.
+    ///
+    /// Unrelated histories are merged too, as if their common ancestor were empty.
+    fn merge_tree(&self, repo: &Repo, one: &str, two: &str) -> Result<MergeTree, GitError>;
+
+    /// `git grep --files-with-matches -E <pattern> -- <paths>`, the files relative to the directory
+    /// git runs from like the pathspecs.
+    fn grep(&self, repo: &Repo, pattern: &str, paths: &[PathBuf])
+        -> Result<Vec<PathBuf>, GitError>;
+
+    /// Whether all `paths` are known to the index (`git ls-files --error-unmatch`).
+    fn ls_files(&self, repo: &Repo, paths: &[PathBuf]) -> Result<bool, GitError>;
+
+    /// Files with unstaged changes (`git diff --name-only -- <paths>`), relative to the top-level
+    /// directory as git diff prints them, not to the directory git runs from.
+    fn diff_name_only(&self, repo: &Repo, paths: &[PathBuf]) -> Result<Vec<PathBuf>, GitError>;
+
+    /// All `(key, value)` pairs of a config file, in file order.
+    fn config_list(&self, repo: &Repo, file: &Path) -> Result<Vec<(String, String)>, GitError>;
+
+    /// `git config --file <file> <key> <value>`
+    fn config_set(&self, repo: &Repo, file: &Path, key: &str, value: &str) -> Result<(), GitError>;
+
+    /// `git config --file <file> --unset <key>`
+    fn config_unset(&self, repo: &Repo, file: &Path, key: &str) -> Result<(), GitError>;
+
+    /// `git config --file <file> --remove-section <section>`, a missing section is not an error.
+    fn config_remove_section(
+        &self,
+        repo: &Repo,
+        file: &Path,
+        section: &str,
+    ) -> Result<(), GitError>;
+
+    /// Values of the keys starting with `prefix` in every config file git reads for the
+    /// repository, lowest precedence first (`git config --show-scope --get-regexp`).
+    fn config_scoped(&self, repo: &Repo, prefix: &str) -> Result<Vec<Setting>, GitError>;
+
+    /// `git config --get <key>`: the value that wins, `None` when the key is not set.
+    fn config_get(&self, repo: &Repo, key: &str) -> Result<Option<String>, GitError>;
+
+    /// `git clone <url> <path>`, `path` being relative to the repository directory.
+    fn clone_repo(&self, repo: &Repo, url: &str, path: &str) -> Result<(), GitError>;
+
+    /// Local branches with the upstream they track, sorted by name.
+    fn local_branches(&self, repo: &Repo) -> Result<Vec<LocalBranch>, GitError>;
+
+    /// Short names of the branches, remote branches and tags (`git for-each-ref`).
+    fn ref_names(&self, repo: &Repo) -> Result<Vec<String>, GitError>;
+
+    /// `git ls-files -z`, relative to the repository directory.
+    fn tracked_files(&self, repo: &Repo) -> Result<Vec<PathBuf>, GitError>;
+}
+
+/// A branch of `refs/heads`, as listed by [`Backend::local_branches`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalBranch {
+    pub name: String,
+    /// Short name of the branch it tracks, e.g. `origin/main`.
+    pub upstream: Option<String>,
+}
+
+/// The backend used by [`Repo::new`]: gitoxide when the feature is enabled, `git` otherwise.
+pub fn default_backend() -> Arc<dyn Backend> {
+    #[cfg(feature = "gitoxide")]
+    return Arc::new(GitoxideBackend::default());
+    #[cfg(not(feature = "gitoxide"))]
+    return Arc::new(Subprocess);
+}
+
+/// Hashes printed by a `git merge-base` command, none when the histories are unrelated.
+fn merge_base_output(repo: &Repo, cmd: &mut Command) -> Result<Vec<String>, GitError> {
+    let output = repo.run_output(cmd)?;
+
+    // git merge-base exits with 1 and prints nothing when the histories are unrelated
+    if output.status.code() == Some(1) && output.stderr.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !output.status.success() {
+        return Err(GitError::failed(cmd, output.status.code(), &output.stderr));
+    }
+
+    let stdout = into_string(cmd, output.stdout)?;
+    Ok(stdout.lines().map(str::to_owned).collect())
+}
+
+/// Backend running the `git` executable.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Subprocess;
+
+impl Backend for Subprocess {
+    fn rev_parse(&self, repo: &Repo, rev: &str) -> Result<String, GitError> {
+        let stdout =
+            repo.run_stdout(
+                repo.git()
+                    .args(["rev-parse", "--verify", "--end-of-options", rev]),
+            )?;
+        Ok(stdout.trim().to_string())
+    }
+
+    fn top_level_dir(&self, repo: &Repo) -> Result<PathBuf, GitError> {
+        let stdout = repo.run_stdout_bytes(repo.git().args(["rev-parse", "--show-toplevel"]))?;
+        Ok(path_from_bytes(
+            stdout.strip_suffix(b"\n").unwrap_or(&stdout),
+        ))
+    }
+
+    fn merge_base(&self, repo: &Repo, one: &str, two: &str) -> Result<Option<String>, GitError> {
+        let mut cmd = repo.git();
+        cmd.args(["merge-base", "--end-of-options", one, two]);
+        Ok(merge_base_output(repo, &mut cmd)?.into_iter().next())
+    }
+
+    fn merge_bases(&self, repo: &Repo, revs: &[&str]) -> Result<Vec<String>, GitError> {
+        let mut cmd = repo.git();
+        cmd.args(["merge-base", "--all"]);
+        if revs.len() > 2 {
+            cmd.arg("--octopus");
+        }
+        cmd.arg("--end-of-options").args(revs);
+        merge_base_output(repo, &mut cmd)
+    }
+
+    fn log(
+        &self,
+        repo: &Repo,
+        revs: &[&str],
+        hidden: &[&str],
+        max_count: Option<usize>,
+        filter: &LogFilter,
+    ) -> Result<Vec<Commit>, GitError> {
+        let mut cmd = repo.git();
+        cmd.args(["log", "-z", "--no-color"])
+            .arg(format!("--format={LOG_FORMAT}"));
+        if let Some(max_count) = max_count {
+            cmd.arg(format!("--max-count={max_count}"));
+        }
+        cmd.args(filter.args())
+            .arg("--end-of-options")
+            .args(revs)
+            .args(hidden.iter().map(|rev| format!("^{rev}")))
+            .arg("--")
+            .args(&filter.paths);
+        // messages and names are whatever bytes the author wrote, not always UTF-8
+        let stdout = repo.run_stdout_bytes(&mut cmd)?;
+        parse_log(&String::from_utf8_lossy(&stdout))
+            .map_err(|message| GitError::parse(&cmd, message))
+    }
+
+    fn count(
+        &self,
+        repo: &Repo,
+        revs: &[&str],
+        hidden: &[&str],
+        filter: &LogFilter,
+    ) -> Result<usize, GitError> {
+        let mut cmd = repo.git();
+        cmd.args(["rev-list", "--count"])
+            .args(filter.args())
+            .arg("--end-of-options")
+            .args(revs)
+            .args(hidden.iter().map(|rev| format!("^{rev}")))
+            .arg("--")
+            .args(&filter.paths);
+        let stdout = repo.run_stdout(&mut cmd)?;
+        stdout
+            .trim()
+            .parse()
+            .map_err(|_| GitError::parse(&cmd, format!("invalid count `{}`", stdout.trim())))
+    }
+
+    fn patch_ids(
+        &self,
+        repo: &Repo,
+        revs: &[&str],
+        hidden: &[&str],
+    ) -> Result<HashMap<String, String>, GitError> {
+        let mut log = repo.git();
+        log.args([
+            "log",
+            "--patch",
+            "--no-merges",
+            "--no-color",
+            "--no-ext-diff",
+            "--no-textconv",
+            "--format=commit %H",
+            "--end-of-options",
+        ])
+        .args(revs)
+        .args(hidden.iter().map(|rev| format!("^{rev}")))
+        .arg("--");
+        let patches = repo.run_stdout_bytes(&mut log)?;
+        if patches.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut cmd = repo.git();
+        cmd.args(["patch-id", "--stable"]);
+        let output = repo.run_output_with_input(&mut cmd, &patches)?;
+        if !output.status.success() {
+            return Err(GitError::failed(&cmd, output.status.code(), &output.stderr));
+        }
+        // one `<patch id> <commit>` line per commit with a diff
+        into_string(&cmd, output.stdout)?
+            .lines()
+            .map(|line| match line.split_once(' ') {
+                Some((patch_id, commit)) => Ok((commit.to_string(), patch_id.to_string())),
+                None => Err(GitError::parse(&cmd, format!("unexpected line `{line}`"))),
+            })
+            .collect()
+    }
+
+    fn diff_stat(&self, repo: &Repo, from: &str, to: &str) -> Result<Vec<FileStat>, GitError> {
+        let mut cmd = repo.git();
+        cmd.args([
+            "diff",
+            "--numstat",
+            "-z",
+            "--no-renames",
+            "--no-ext-diff",
+            "--no-textconv",
+            "--end-of-options",
+            from,
+            to,
+            "--",
+        ]);
+        let stdout = repo.run_stdout_bytes(&mut cmd)?;
+        parse_numstat(&stdout).map_err(|message| GitError::parse(&cmd, message))
+    }
+
+    fn merge_tree(&self, repo: &Repo, one: &str, two: &str) -> Result<MergeTree, GitError> {
+        let mut cmd = repo.git();
+        cmd.args([
+            "merge-tree",
+            "--write-tree",
+            "-z",
+            "--allow-unrelated-histories",
+            "--end-of-options",
+            one,
+            two,
+        ]);
+        let output = repo.run_output(&mut cmd)?;
+        // 1 means the merge has conflicts, anything else that it could not be done
+        if !matches!(output.status.code(), Some(0 | 1)) {
+            return Err(GitError::failed(&cmd, output.status.code(), &output.stderr));
+        }
+        parse_merge_tree(&output.stdout).map_err(|message| GitError::parse(&cmd, message))
+    }
+
+    fn grep(
+        &self,
+        repo: &Repo,
+        pattern: &str,
+        paths: &[PathBuf],
+    ) -> Result<Vec<PathBuf>, GitError> {
+        let mut cmd = repo.git();
+        cmd.args([
+            "--no-pager",
+            "grep",
+            "--files-with-matches",
+            "--name-only",
+            "-I", // ignore binary files
+            "-z", // null character as delimiter
+            "-E", // extended regex
+            "-e", // pattern
+            pattern,
+            "--",
+        ])
+        .args(paths);
+        let output = repo.run_output(&mut cmd)?;
+
+        // git grep exits with 1 when nothing matched
+        if output.status.code() == Some(1) && output.stderr.is_empty() {
+            return Ok(Vec::new());
+        }
+        if !output.status.success() {
+            return Err(GitError::failed(&cmd, output.status.code(), &output.stderr));
+        }
+        Ok(split_nul_paths(&output.stdout))
+    }
+
+    fn ls_files(&self, repo: &Repo, paths: &[PathBuf]) -> Result<bool, GitError> {
+        let output = repo.run_output(
+            repo.git()
+                .args(["ls-files", "--error-unmatch", "--"])
+                .args(paths),
+        )?;
+        Ok(output.status.success())
+    }
+
+    fn diff_name_only(&self, repo: &Repo, paths: &[PathBuf]) -> Result<Vec<PathBuf>, GitError> {
+        let stdout = repo.run_stdout_bytes(
+            repo.git()
+                .args(["--no-pager", "diff", "--name-only", "-z", "--"])
+                .args(paths),
+        )?;
+        Ok(split_nul_paths(&stdout))
+    }
+
+    fn config_list(&self, repo: &Repo, file: &Path) -> Result<Vec<(String, String)>, GitError> {
+        let stdout = repo.run_stdout(
+            repo.git()
+                .args(["config", "--file"])
+                .arg(file)
+                .args(["--list", "-z"]),
+        )?;
+        // with -z every entry is `key\nvalue\0`
+        Ok(split_nul(&stdout)
+            .into_iter()
+            .map(|entry| match entry.split_once('\n') {
+                Some((key, value)) => (key.to_string(), value.to_string()),
+                None => (entry, String::new()),
+            })
+            .collect())
+    }
+
+    fn config_set(&self, repo: &Repo, file: &Path, key: &str, value: &str) -> Result<(), GitError> {
+        repo.run_stdout(
+            repo.git()
+                .args(["config", "--file"])
+                .arg(file)
+                .args([key, value]),
+        )?;
+        Ok(())
+    }
+
+    fn config_unset(&self, repo: &Repo, file: &Path, key: &str) -> Result<(), GitError> {
+        repo.run_stdout(
+            repo.git()
+                .args(["config", "--file"])
+                .arg(file)
+                .args(["--unset", key]),
+        )?;
+        Ok(())
+    }
+
+    fn config_remove_section(
+        &self,
+        repo: &Repo,
+        file: &Path,
+        section: &str,
+    ) -> Result<(), GitError> {
+        let _ = repo.run_output(
+            repo.git()
+                .args(["config", "--file"])
+                .arg(file)
+                .args(["--remove-section", section])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null()),
+        )?;
+        Ok(())
+    }
+
+    fn config_scoped(&self, repo: &Repo, prefix: &str) -> Result<Vec<Setting>, GitError> {
+        let mut cmd = repo.git();
+        cmd.args([
+            "config",
+            "--null",
+            "--show-scope",
+            "--show-origin",
+            "--get-regexp",
+        ])
+        .arg(format!("^{}", prefix.replace('.', "\\.")));
+        let output = repo.run_output(&mut cmd)?;
+        // --get-regexp exits with 1 when nothing matches
+        if output.status.code() == Some(1) && output.stderr.is_empty() {
+            return Ok(Vec::new());
+        }
+        if !output.status.success() {
+            return Err(GitError::failed(&cmd, output.status.code(), &output.stderr));
+        }
+
+        // every value is `scope\0origin\0key\nvalue\0`, a key without `\n` has no value
+        let stdout = into_string(&cmd, output.stdout)?;
+        let fields = split_nul(&stdout);
+        if !fields.len().is_multiple_of(3) {
+            return Err(GitError::parse(&cmd, "truncated config entry"));
+        }
+        fields
+            .chunks(3)
+            .map(|entry| {
+                let scope: Scope = entry[0]
+                    .parse()
+                    .map_err(|_| GitError::parse(&cmd, format!("unknown scope {}", entry[0])))?;
+                let (key, value) = match entry[2].split_once('\n') {
+                    Some((key, value)) => (key, value),
+                    // `[section] key` alone means true
+                    None => (entry[2].as_str(), "true"),
+                };
+                Ok(Setting {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    scope,
+                    origin: Some(entry[1].clone()),
+                })
+            })
+            .collect()
+    }
+
+    fn config_get(&self, repo: &Repo, key: &str) -> Result<Option<String>, GitError> {
+        let mut cmd = repo.git();
+        cmd.args(["config", "--get", key]);
+        let output = repo.run_output(&mut cmd)?;
+        if output.status.code() == Some(1) && output.stderr.is_empty() {
+            return Ok(None);
+        }
+        if !output.status.success() {
+            return Err(GitError::failed(&cmd, output.status.code(), &output.stderr));
+        }
+        let stdout = into_string(&cmd, output.stdout)?;
+        Ok(Some(
+            stdout.strip_suffix('\n').unwrap_or(&stdout).to_string(),
+        ))
+    }
+
+    fn clone_repo(&self, repo: &Repo, url: &str, path: &str) -> Result<(), GitError> {
+        let mut cmd = repo.git();
+        cmd.args(["clone", "--quiet", url, path])
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit());
+        let output = repo.run_output(&mut cmd)?;
+        if !output.status.success() {
+            return Err(GitError::failed(&cmd, output.status.code(), &output.stderr));
+        }
+        Ok(())
+    }
+
+    fn local_branches(&self, repo: &Repo) -> Result<Vec<LocalBranch>, GitError> {
+        let mut cmd = repo.git();
+        cmd.args([
+            "for-each-ref",
+            "--format=%(refname:short)%00%(upstream:short)",
+            "refs/heads",
+        ]);
+        let stdout = repo.run_stdout(&mut cmd)?;
+        stdout
+            .lines()
+            .map(|line| match line.split_once('\0') {
+                Some((name, upstream)) => Ok(LocalBranch {
+                    name: name.to_string(),
+                    upstream: Some(upstream.to_string()).filter(|upstream| !upstream.is_empty()),
+                }),
+                None => Err(GitError::parse(&cmd, format!("unexpected line `{line}`"))),
+            })
+            .collect()
+    }
+
+    fn ref_names(&self, repo: &Repo) -> Result<Vec<String>, GitError> {
+        let mut cmd = repo.git();
+        cmd.args([
+            "for-each-ref",
+            "--format=%(refname:short)",
+            "refs/heads",
+            "refs/remotes",
+            "refs/tags",
+        ]);
+        let stdout = repo.run_stdout(&mut cmd)?;
+        Ok(stdout.lines().map(str::to_owned).collect())
+    }
+
+    fn tracked_files(&self, repo: &Repo) -> Result<Vec<PathBuf>, GitError> {
+        let stdout = repo.run_stdout_bytes(repo.git().args(["ls-files", "-z"]))?;
+        Ok(split_nul_paths(&stdout))
+    }
+}
@@ -0,0 +1,587 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{FixedOffset, TimeZone};
+use gix::{
+    bstr::{BStr, BString, ByteSlice},
+    index::entry::{Flags, Mode},
+    pathspec::Search,
+    refs::{FullName, FullNameRef},
+    remote::Direction,
+    revision::walk::Sorting,
+    status::UntrackedFiles,
+};
+use regex::bytes::Regex;
+
+use super::{Backend, LocalBranch, Subprocess};
+use crate::{
+    diff::FileStat,
+    file::relative_to,
+    log::{Commit, LogFilter, Signature},
+    merge::MergeTree,
+    path_from_bytes,
+    settings::Setting,
+    GitError, Repo,
+};
+
+/// Backend answering rev-parse, merge-base, log, rev-list --count, grep, ls-files, diff
+/// --name-only and the branch and ref listings in-process with gitoxide.
+///
+/// grep matches with the `regex` crate, so a pattern it can't parse, e.g. one with
+/// back-references, is left to git. Patch IDs, diff stats, merges, config and clone have no
+/// in-process equivalent yet and run `git` too, like log and rev-list --count do when filtering
+/// commits.
+#[derive(Debug, Default)]
+pub struct GitoxideBackend {
+    /// Repositories opened so far, keyed by the git dir or directory they were found from.
+    repos: Mutex<HashMap<PathBuf, gix::ThreadSafeRepository>>,
+}
+
+fn backend_error(operation: &str, err: impl std::fmt::Display) -> GitError {
+    GitError::Backend {
+        operation: operation.to_string(),
+        message: err.to_string(),
+    }
+}
+
+impl GitoxideBackend {
+    fn open(&self, repo: &Repo) -> Result<gix::Repository, GitError> {
+        let key = repo.git_dir().unwrap_or(repo.work_tree()).to_path_buf();
+        let mut repos = self.repos.lock().expect("repository cache poisoned");
+        if let Some(opened) = repos.get(&key) {
+            return Ok(opened.to_thread_local());
+        }
+        let opened = match repo.git_dir() {
+            Some(git_dir) => gix::ThreadSafeRepository::open(git_dir)
+                .map_err(|err| backend_error("open", err))?,
+            None => gix::ThreadSafeRepository::discover(repo.work_tree())
+                .map_err(|err| backend_error("discover", err))?,
+        };
+        let local = opened.to_thread_local();
+        repos.insert(key, opened);
+        Ok(local)
+    }
+
+    fn resolve(&self, local: &gix::Repository, rev: &str) -> Result<gix::ObjectId, GitError> {
+        local
+            .rev_parse_single(rev)
+            .map(|id| id.detach())
+            .map_err(|err| backend_error("rev-parse", err))
+    }
+
+    /// Directory git runs from for `repo`, relative to the top-level directory `workdir`.
+    fn prefix(&self, repo: &Repo, workdir: &Path) -> Result<PathBuf, GitError> {
+        let cwd = repo.work_tree().canonicalize()?;
+        Ok(cwd
+            .strip_prefix(workdir)
+            .map(Path::to_path_buf)
+            .unwrap_or_default())
+    }
+}
+
+/// `paths` as git reads pathspecs given in `prefix`; with none and a prefix, everything below it.
+///
+/// gix's own pathspecs take their prefix from the current directory of the process, which is not
+/// necessarily the directory of `repo`.
+fn pathspec(
+    local: &gix::Repository,
+    operation: &str,
+    paths: &[PathBuf],
+    prefix: Option<&Path>,
+    workdir: &Path,
+) -> Result<Search, GitError> {
+    let defaults = local
+        .pathspec_defaults()
+        .map_err(|err| backend_error(operation, err))?;
+    let patterns = paths
+        .iter()
+        .map(|path| {
+            let spec = gix::path::into_bstr(path.as_path());
+            // gix matches nothing with a pathspec for the top-level directory itself, e.g. `.`
+            // there or `..` below it, where git matches everything
+            let top_level = !spec.starts_with(b":")
+                && normalize(&prefix.unwrap_or(Path::new("")).join(path)).is_empty();
+            gix::pathspec::parse(if top_level { b":(top)" } else { &spec }, defaults)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| backend_error(operation, err))?;
+    Search::from_specs(patterns, prefix, workdir).map_err(|err| backend_error(operation, err))
+}
+
+/// Whether the top-level relative `path` is one `search` selects.
+fn selects(search: &mut Search, path: &BStr) -> bool {
+    search
+        .pattern_matching_relative_path(path, Some(false), &mut |_, _, _, _| false)
+        .is_some_and(|found| !found.is_excluded())
+}
+
+impl Backend for GitoxideBackend {
+    fn rev_parse(&self, repo: &Repo, rev: &str) -> Result<String, GitError> {
+        let local = self.open(repo)?;
+        Ok(self.resolve(&local, rev)?.to_string())
+    }
+
+    fn top_level_dir(&self, repo: &Repo) -> Result<PathBuf, GitError> {
+        let local = self.open(repo)?;
+        let workdir = local
+            .workdir()
+            .ok_or_else(|| backend_error("rev-parse --show-toplevel", "repository is bare"))?;
+        Ok(workdir.canonicalize()?)
+    }
+
+    fn merge_base(&self, repo: &Repo, one: &str, two: &str) -> Result<Option<String>, GitError> {
+        let local = self.open(repo)?;
+        let one = self.resolve(&local, one)?;
+        let two = self.resolve(&local, two)?;
+        match local.merge_base(one, two) {
+            Ok(base) => Ok(Some(base.to_string())),
+            Err(gix::repository::merge_base::Error::NotFound { .. }) => Ok(None),
+            Err(err) => Err(backend_error("merge-base", err)),
+        }
+    }
+
+    fn merge_bases(&self, repo: &Repo, revs: &[&str]) -> Result<Vec<String>, GitError> {
+        let local = self.open(repo)?;
+        let revs = revs
+            .iter()
+            .map(|rev| self.resolve(&local, rev))
+            .collect::<Result<Vec<_>, _>>()?;
+        let Some((first, others)) = revs.split_first() else {
+            return Err(backend_error("merge-base", "no revisions given"));
+        };
+
+        // like git merge-base --octopus, add one revision at a time, keeping every base so far
+        let mut bases = vec![*first];
+        for other in others {
+            let mut next = Vec::new();
+            for base in &bases {
+                let found = local
+                    .merge_bases_many(*base, &[*other])
+                    .map_err(|err| backend_error("merge-base", err))?;
+                for id in found {
+                    if !next.contains(&id.detach()) {
+                        next.push(id.detach());
+                    }
+                }
+            }
+            bases = next;
+        }
+        Ok(bases.iter().map(ToString::to_string).collect())
+    }
+
+    fn log(
+        &self,
+        repo: &Repo,
+        revs: &[&str],
+        hidden: &[&str],
+        max_count: Option<usize>,
+        filter: &LogFilter,
+    ) -> Result<Vec<Commit>, GitError> {
+        if !filter.is_empty() {
+            return Subprocess.log(repo, revs, hidden, max_count, filter);
+        }
+        let local = self.open(repo)?;
+        let tips = revs
+            .iter()
+            .map(|rev| self.resolve(&local, rev))
+            .collect::<Result<Vec<_>, _>>()?;
+        let hidden = hidden
+            .iter()
+            .map(|rev| self.resolve(&local, rev))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let walk = local
+            .rev_walk(tips)
+            .sorting(Sorting::ByCommitTime(Default::default()))
+            .with_hidden(hidden)
+            .all()
+            .map_err(|err| backend_error("log", err))?;
+
+        let mut commits = Vec::new();
+        for info in walk.take(max_count.unwrap_or(usize::MAX)) {
+            let info = info.map_err(|err| backend_error("log", err))?;
+            let object = info.object().map_err(|err| backend_error("log", err))?;
+            let commit = object.decode().map_err(|err| backend_error("log", err))?;
+            let message = commit.message();
+            commits.push(Commit {
+                hash: info.id.to_string(),
+                parents: commit.parents().map(|id| id.to_string()).collect(),
+                author: signature(commit.author())?,
+                committer: signature(commit.committer())?,
+                subject: message.summary().to_string(),
+                body: message
+                    .body
+                    .map(|body| body.to_string().trim_end_matches('\n').to_string())
+                    .unwrap_or_default(),
+            });
+        }
+        Ok(commits)
+    }
+
+    fn count(
+        &self,
+        repo: &Repo,
+        revs: &[&str],
+        hidden: &[&str],
+        filter: &LogFilter,
+    ) -> Result<usize, GitError> {
+        if !filter.is_empty() {
+            return Subprocess.count(repo, revs, hidden, filter);
+        }
+        let local = self.open(repo)?;
+        let tips = revs
+            .iter()
+            .map(|rev| self.resolve(&local, rev))
+            .collect::<Result<Vec<_>, _>>()?;
+        let hidden = hidden
+            .iter()
+            .map(|rev| self.resolve(&local, rev))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let walk = local
+            .rev_walk(tips)
+            .with_hidden(hidden)
+            .all()
+            .map_err(|err| backend_error("rev-list", err))?;
+        let mut count = 0;
+        for info in walk {
+            info.map_err(|err| backend_error("rev-list", err))?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn patch_ids(
+        &self,
+        repo: &Repo,
+        revs: &[&str],
+        hidden: &[&str],
+    ) -> Result<HashMap<String, String>, GitError> {
+        Subprocess.patch_ids(repo, revs, hidden)
+    }
+
+    fn diff_stat(&self, repo: &Repo, from: &str, to: &str) -> Result<Vec<FileStat>, GitError> {
+        Subprocess.diff_stat(repo, from, to)
+    }
+
+    fn merge_tree(&self, repo: &Repo, one: &str, two: &str) -> Result<MergeTree, GitError> {
+        Subprocess.merge_tree(repo, one, two)
+    }
+
+    fn grep(
+        &self,
+        repo: &Repo,
+        pattern: &str,
+        paths: &[PathBuf],
+    ) -> Result<Vec<PathBuf>, GitError> {
+        // git's extended regexps are mostly Rust's, leave the rest to git
+        let Ok(regex) = Regex::new(pattern) else {
+            return Subprocess.grep(repo, pattern, paths);
+        };
+        let local = self.open(repo)?;
+        let workdir = self.top_level_dir(repo)?;
+        let prefix = self.prefix(repo, &workdir)?;
+        let mut search = pathspec(&local, "grep", paths, Some(&prefix), &workdir)?;
+        let index = local
+            .index_or_empty()
+            .map_err(|err| backend_error("grep", err))?;
+
+        let mut files = Vec::new();
+        let mut previous: Option<&BStr> = None;
+        for entry in index.entries() {
+            let path = entry.path(&index);
+            // unmerged files have an entry per stage, symlinks and submodules are not searched
+            if previous == Some(path)
+                || !matches!(entry.mode, Mode::FILE | Mode::FILE_EXECUTABLE)
+                || !selects(&mut search, path)
+            {
+                continue;
+            }
+            previous = Some(path);
+
+            let content = if entry.flags.contains(Flags::SKIP_WORKTREE) {
+                local
+                    .find_blob(entry.id)
+                    .map_err(|err| backend_error("grep", err))?
+                    .take_data()
+            } else {
+                match fs::read(workdir.join(gix::path::from_bstr(path))) {
+                    Ok(content) => content,
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                    Err(err) => return Err(err.into()),
+                }
+            };
+            // -I: git takes a NUL in the first 8000 bytes for a binary file
+            if content[..content.len().min(8000)].contains(&0) {
+                continue;
+            }
+            if content
+                .split(|byte| *byte == b'\n')
+                .any(|line| regex.is_match(line))
+            {
+                files.push(relative_to(&path_from_bytes(path), &prefix));
+            }
+        }
+        Ok(files)
+    }
+
+    fn ls_files(&self, repo: &Repo, paths: &[PathBuf]) -> Result<bool, GitError> {
+        let local = self.open(repo)?;
+        let workdir = self.top_level_dir(repo)?;
+        let cwd = repo.work_tree().canonicalize()?;
+        let index = local
+            .index_or_empty()
+            .map_err(|err| backend_error("ls-files", err))?;
+
+        for path in paths {
+            let wanted = match cwd.join(path).strip_prefix(&workdir) {
+                Ok(relative) => normalize(relative),
+                Err(_) => return Ok(false),
+            };
+            let tracked = index.entries().iter().any(|entry| {
+                let entry_path: &[u8] = entry.path(&index);
+                wanted.is_empty()
+                    || entry_path == wanted
+                    || entry_path
+                        .strip_prefix(wanted.as_slice())
+                        .is_some_and(|rest| rest.starts_with(b"/"))
+            });
+            if !tracked {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn diff_name_only(&self, repo: &Repo, paths: &[PathBuf]) -> Result<Vec<PathBuf>, GitError> {
+        let local = self.open(repo)?;
+        let workdir = self.top_level_dir(repo)?;
+        let prefix = self.prefix(repo, &workdir)?;
+        // unlike grep, git diff without pathspecs looks at the whole work tree
+        let prefix = (!paths.is_empty()).then_some(prefix.as_path());
+        let mut search = pathspec(&local, "diff", paths, prefix, &workdir)?;
+
+        let changes = local
+            .status(gix::progress::Discard)
+            .map_err(|err| backend_error("diff", err))?
+            .untracked_files(UntrackedFiles::None)
+            .into_index_worktree_iter(Vec::<BString>::new())
+            .map_err(|err| backend_error("diff", err))?;
+        let mut files = Vec::new();
+        for change in changes {
+            let change = change.map_err(|err| backend_error("diff", err))?;
+            // `None` for entries whose stat changed but whose content did not
+            if change.summary().is_some() && selects(&mut search, change.rela_path()) {
+                files.push(path_from_bytes(change.rela_path()));
+            }
+        }
+        // the status is computed in parallel, git lists the files in index order
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    fn config_list(&self, repo: &Repo, file: &Path) -> Result<Vec<(String, String)>, GitError> {
+        Subprocess.config_list(repo, file)
+    }
+
+    fn config_set(&self, repo: &Repo, file: &Path, key: &str, value: &str) -> Result<(), GitError> {
+        Subprocess.config_set(repo, file, key, value)
+    }
+
+    fn config_unset(&self, repo: &Repo, file: &Path, key: &str) -> Result<(), GitError> {
+        Subprocess.config_unset(repo, file, key)
+    }
+
+    fn config_remove_section(
+        &self,
+        repo: &Repo,
+        file: &Path,
+        section: &str,
+    ) -> Result<(), GitError> {
+        Subprocess.config_remove_section(repo, file, section)
+    }
+
+    fn config_scoped(&self, repo: &Repo, prefix: &str) -> Result<Vec<Setting>, GitError> {
+        Subprocess.config_scoped(repo, prefix)
+    }
+
+    fn config_get(&self, repo: &Repo, key: &str) -> Result<Option<String>, GitError> {
+        Subprocess.config_get(repo, key)
+    }
+
+    fn clone_repo(&self, repo: &Repo, url: &str, path: &str) -> Result<(), GitError> {
+        Subprocess.clone_repo(repo, url, path)
+    }
+
+    fn local_branches(&self, repo: &Repo) -> Result<Vec<LocalBranch>, GitError> {
+        let local = self.open(repo)?;
+        let short_names = ShortNames::new(&local)?;
+        let mut branches = Vec::new();
+        for name in short_names.names_under(b"refs/heads/") {
+            let upstream = upstream(&local, name.as_ref())?;
+            branches.push(LocalBranch {
+                name: short_names.shorten(name.as_bstr()),
+                upstream: upstream.map(|upstream| short_names.shorten(upstream.as_bstr())),
+            });
+        }
+        Ok(branches)
+    }
+
+    fn ref_names(&self, repo: &Repo) -> Result<Vec<String>, GitError> {
+        let local = self.open(repo)?;
+        let short_names = ShortNames::new(&local)?;
+        let mut names = Vec::new();
+        for prefix in [&b"refs/heads/"[..], b"refs/remotes/", b"refs/tags/"] {
+            names.extend(
+                short_names
+                    .names_under(prefix)
+                    .map(|name| short_names.shorten(name.as_bstr())),
+            );
+        }
+        Ok(names)
+    }
+
+    fn tracked_files(&self, repo: &Repo) -> Result<Vec<PathBuf>, GitError> {
+        Subprocess.tracked_files(repo)
+    }
+}
+
+fn signature(signature: gix::actor::SignatureRef<'_>) -> Result<Signature, GitError> {
+    let time = signature.time().map_err(|err| backend_error("log", err))?;
+    let date = FixedOffset::east_opt(time.offset)
+        .and_then(|offset| offset.timestamp_opt(time.seconds, 0).single())
+        .ok_or_else(|| backend_error("log", format!("invalid time of {}", signature.name)))?;
+    Ok(Signature {
+        name: signature.name.to_string(),
+        email: signature.email.to_string(),
+        date,
+    })
+}
+
+/// What `%(upstream)` shows for the branch `name`: the ref it merges when it tracks another local
+/// branch (remote `.`), else the remote-tracking ref that ref is fetched into.
+fn upstream(local: &gix::Repository, name: &FullNameRef) -> Result<Option<FullName>, GitError> {
+    let tracks_local = local
+        .branch_remote_name(name.shorten(), Direction::Fetch)
+        .is_some_and(|remote| remote.as_bstr() == ".");
+    let upstream = if tracks_local {
+        local
+            .branch_remote_ref_name(name, Direction::Fetch)
+            .transpose()
+            .map_err(|err| backend_error("for-each-ref", err))?
+    } else {
+        local
+            .branch_remote_tracking_ref_name(name, Direction::Fetch)
+            .transpose()
+            .map_err(|err| backend_error("for-each-ref", err))?
+    };
+    Ok(upstream.map(Cow::into_owned))
+}
+
+/// Rules git tries a short ref name with, as `(prefix, suffix)`, in order of precedence.
+const REF_RULES: [(&str, &str); 6] = [
+    ("", ""),
+    ("refs/", ""),
+    ("refs/tags/", ""),
+    ("refs/heads/", ""),
+    ("refs/remotes/", ""),
+    ("refs/remotes/", "/HEAD"),
+];
+
+/// All references of a repository, to shorten their names like `%(refname:short)` does.
+struct ShortNames {
+    /// Full names, sorted like git for-each-ref lists them.
+    names: Vec<FullName>,
+    existing: HashSet<BString>,
+    git_dir: PathBuf,
+}
+
+impl ShortNames {
+    fn new(local: &gix::Repository) -> Result<Self, GitError> {
+        let references = local
+            .references()
+            .map_err(|err| backend_error("for-each-ref", err))?;
+        let mut names = Vec::new();
+        for reference in references
+            .all()
+            .map_err(|err| backend_error("for-each-ref", err))?
+        {
+            let reference = reference.map_err(|err| backend_error("for-each-ref", err))?;
+            names.push(reference.name().to_owned());
+        }
+        names.sort();
+        Ok(ShortNames {
+            existing: names.iter().map(|name| name.as_bstr().to_owned()).collect(),
+            names,
+            git_dir: local.git_dir().to_path_buf(),
+        })
+    }
+
+    fn names_under<'a>(&'a self, prefix: &'a [u8]) -> impl Iterator<Item = &'a FullName> {
+        self.names
+            .iter()
+            .filter(move |name| name.as_bstr().starts_with(prefix))
+    }
+
+    /// Whether git would find a reference named `name`, `HEAD` and other root refs included.
+    fn exists(&self, name: &[u8]) -> bool {
+        self.existing.contains(name.as_bstr())
+            || (!name.is_empty()
+                && name
+                    .iter()
+                    .all(|byte| byte.is_ascii_uppercase() || *byte == b'_')
+                && self
+                    .git_dir
+                    .join(gix::path::from_byte_slice(name))
+                    .is_file())
+    }
+
+    /// The shortest name of `full` no rule would take for another reference, as git shortens it
+    /// with core.warnAmbiguousRefs on.
+    fn shorten(&self, full: &BStr) -> String {
+        // the last rule makes a branch `origin` ambiguous, but leaves `origin/HEAD` as it is
+        let shortening = REF_RULES.iter().enumerate().take(REF_RULES.len() - 1);
+        for (rule, (prefix, suffix)) in shortening.skip(1).rev() {
+            let Some(short) = full
+                .strip_prefix(prefix.as_bytes())
+                .and_then(|rest| rest.strip_suffix(suffix.as_bytes()))
+                .filter(|short| !short.is_empty())
+            else {
+                continue;
+            };
+            let ambiguous = REF_RULES
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != rule)
+                .any(|(_, (prefix, suffix))| {
+                    self.exists(&[prefix.as_bytes(), short, suffix.as_bytes()].concat())
+                });
+            if !ambiguous {
+                return short.to_str_lossy().into_owned();
+            }
+        }
+        full.to_str_lossy().into_owned()
+    }
+}
+
+/// Turn a path relative to the work tree into the bytes of its index entry, resolving `.` and
+/// `..`.
+fn normalize(path: &Path) -> Vec<u8> {
+    let mut parts: Vec<&[u8]> = Vec::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::ParentDir => {
+                parts.pop();
+            }
+            std::path::Component::Normal(part) => parts.push(part.as_encoded_bytes()),
+            _ => {}
+        }
+    }
+    parts.join(&b'/')
+}
@@ -0,0 +1,57 @@
+//! Command line flags every tool takes.
+
+use std::path::PathBuf;
+
+use crate::{
+    report::Format,
+    terminal::{self, ColorChoice},
+    trace, GitError, Repo,
+};
+
+/// Output, logging, color and pager flags, flattened into the arguments of every tool.
+#[derive(clap::Args, Debug, Default)]
+pub struct GlobalArgs {
+    /// Output format [default: the tool's excess.*.format setting or human]
+    #[clap(long, global = true, possible_values = Format::NAMES)]
+    pub format: Option<Format>,
+    /// Log every command run, with its environment, exit status and duration, on stderr
+    #[clap(long, short, global = true)]
+    pub verbose: bool,
+    /// Append the command log to this file, e.g. to attach it to a bug report
+    #[clap(long, global = true, value_name = "PATH", parse(from_os_str))]
+    pub trace_file: Option<PathBuf>,
+    /// When to color the output [default: color.ui or auto]
+    #[clap(
+        long,
+        global = true,
+        value_name = "WHEN",
+        possible_values = ColorChoice::NAMES,
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "always"
+    )]
+    pub color: Option<ColorChoice>,
+    /// Print to stdout instead of through the pager
+    #[clap(long, global = true)]
+    pub no_pager: bool,
+}
+
+impl GlobalArgs {
+    /// Start the command log, then set up colors and the pager for `repo`.
+    pub fn init(&self, repo: &Repo) -> Result<(), GitError> {
+        trace::init(self.verbose, self.trace_file.as_deref())?;
+        terminal::init(self.color, self.no_pager, repo)
+    }
+}
+
+/// `arg` without its short flag, for a tool that leaves that letter to another program.
+pub fn without_short<'help>(arg: clap::Arg<'help>) -> clap::Arg<'help> {
+    let mut plain = clap::Arg::new(arg.get_id()).global(arg.is_global_set());
+    if let Some(long) = arg.get_long() {
+        plain = plain.long(long);
+    }
+    if let Some(help) = arg.get_help() {
+        plain = plain.help(help);
+    }
+    plain
+}
@@ -0,0 +1,360 @@
+//! Shell completion of the tools' command lines.
+//!
+//! [`script`] prints clap_complete's script for the flags and subcommands, with a hook that first
+//! calls the tool back as `<tool> __complete <index> <words>...`. [`intercept`] answers with the
+//! values the tool knows for the argument being completed, e.g. branch names for git-sdif, one
+//! per line; when it has none, the generated script completes as usual.
+
+use std::{collections::BTreeSet, ffi::OsString, fmt, path::PathBuf, str::FromStr};
+
+use clap::{Arg, Command};
+
+/// First argument of the completion requests sent by the scripts.
+pub const COMPLETE_ARG: &str = "__complete";
+
+/// Candidates for the value of `arg`, identified by its clap id, in the subcommand `path`.
+///
+/// `prefix` is what was typed so far; candidates not starting with it are dropped afterwards.
+pub type ValueCandidates = fn(path: &[&str], arg: &str, prefix: &str) -> Vec<String>;
+
+/// A shell the completion scripts are written for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// Values accepted by `completions <shell>`.
+    pub const NAMES: [&'static str; 3] = ["bash", "zsh", "fish"];
+
+    fn generator(self) -> clap_complete::Shell {
+        match self {
+            Shell::Bash => clap_complete::Shell::Bash,
+            Shell::Zsh => clap_complete::Shell::Zsh,
+            Shell::Fish => clap_complete::Shell::Fish,
+        }
+    }
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(format!(
+                "unknown shell `{value}`, expected one of {}",
+                Shell::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Completion script of `tool`, whose clap definition is `command`, for `shell`.
+///
+/// Tools named `git-<name>` are also completed after `git <name>`, by git's own completion.
+pub fn script(shell: Shell, mut command: Command<'static>, tool: &str) -> String {
+    let mut generated = Vec::new();
+    clap_complete::generate(shell.generator(), &mut command, tool, &mut generated);
+    let mut script = String::from_utf8(generated).expect("clap_complete writes UTF-8");
+    let function = tool.replace('-', "_");
+    let git_command = tool.strip_prefix("git-");
+    match shell {
+        Shell::Bash => {
+            script.push_str(&format!(
+                r#"
+_{function}_values() {{
+    local IFS=$'\n'
+    COMPREPLY=($("{tool}" {COMPLETE_ARG} "$COMP_CWORD" "${{COMP_WORDS[@]}}" 2>/dev/null))
+    unset IFS
+    if [[ ${{#COMPREPLY[@]}} -eq 0 ]]; then
+        _{tool} "{tool}"
+    elif [[ ${{#COMPREPLY[@]}} -eq 1 && ${{COMPREPLY[0]}} == */ ]]; then
+        compopt -o nospace
+    fi
+}}
+complete -F _{function}_values -o bashdefault -o default {tool}
+"#
+            ));
+            if let Some(name) = git_command {
+                // called by git's completion for `git <name>`, with `words` and `cword` set
+                script.push_str(&format!(
+                    r#"_git_{name}() {{
+    local start=${{__git_cmd_idx:-1}}
+    local COMP_WORDS=("{tool}" "${{words[@]:start+1}}") COMP_CWORD=$((cword - start))
+    _{function}_values
+}}
+"#,
+                    name = name.replace('-', "_")
+                ));
+            }
+        }
+        // the generated function completes when autoloaded, rename it to run the hook first; git's
+        // _git also runs `_git-<name>` for `git <name>`, with `words` starting at <name>
+        Shell::Zsh => {
+            let call = format!("_{tool} \"$@\"\n");
+            if script.ends_with(&call) {
+                script.truncate(script.len() - call.len());
+            }
+            script.push_str(&format!(
+                r#"functions[_{tool}_flags]=$functions[_{tool}]
+_{tool}() {{
+    local -a candidates
+    candidates=(${{(f)"$("{tool}" {COMPLETE_ARG} $((CURRENT - 1)) "${{words[@]}}" 2>/dev/null)"}})
+    if (( ${{#candidates}} )); then
+        compadd -Q -a candidates
+    else
+        _{tool}_flags "$@"
+    fi
+}}
+compdef _{tool} {tool}
+"#
+            ));
+        }
+        Shell::Fish => {
+            script.push_str(&format!(
+                r#"function __{function}_values
+    set -l tokens (commandline -opc)
+    # after `git <name>`, complete as if the tool had been called directly
+    if set -q argv[1]
+        set tokens $tokens[(contains -i -- $argv[1] $tokens)..-1]
+    end
+    set -l candidates ("{tool}" {COMPLETE_ARG} (count $tokens) $tokens (commandline -ct) 2>/dev/null)
+    if set -q candidates[1]
+        printf '%s\n' $candidates
+    else
+        __fish_complete_path (commandline -ct)
+    end
+end
+complete -c {tool} -f -a '(__{function}_values)'
+"#
+            ));
+            if let Some(name) = git_command {
+                script.push_str(&format!(
+                    "complete -c git -n '__fish_seen_subcommand_from {name}' -f -a '(__{function}_values {name})'\n"
+                ));
+            }
+        }
+    }
+    script
+}
+
+/// When `args` is a completion request, print the candidates and exit.
+///
+/// Called by the tools before parsing their arguments, since `__complete` is not one of them.
+pub fn intercept(args: &[OsString], command: fn() -> Command<'static>, values: ValueCandidates) {
+    if args.get(1).is_none_or(|arg| arg != COMPLETE_ARG) {
+        return;
+    }
+    let index = args
+        .get(2)
+        .and_then(|index| index.to_str())
+        .and_then(|index| index.parse().ok())
+        .unwrap_or(0);
+    let words: Vec<String> = args
+        .iter()
+        .skip(3)
+        .map(|word| word.to_string_lossy().into_owned())
+        .collect();
+    for candidate in complete(command(), &words, index, values) {
+        println!("{candidate}");
+    }
+    std::process::exit(0);
+}
+
+/// Values the tool knows for `words[index]`, `words[0]` being the program name.
+///
+/// Flags, subcommands and possible values are left to the generated script, like the arguments
+/// the tool has no values for.
+pub fn complete(
+    mut command: Command<'static>,
+    words: &[String],
+    index: usize,
+    values: ValueCandidates,
+) -> Vec<String> {
+    command.build();
+    let current = words.get(index).map_or("", String::as_str);
+    let mut commands = vec![&command];
+    let mut path: Vec<&str> = Vec::new();
+    let mut positionals = 0;
+    let mut pending: Option<&Arg> = None;
+    let mut raw = false;
+
+    for word in words.iter().take(index).skip(1) {
+        let cmd = *commands.last().unwrap();
+        // bash splits `--format=json` into three words
+        if word == "=" && pending.is_some() {
+            continue;
+        }
+        if pending.take().is_some() {
+            continue;
+        }
+        if raw {
+            positionals += 1;
+        } else if word == "--" {
+            raw = true;
+        } else if let Some(long) = word.strip_prefix("--") {
+            if !long.contains('=') {
+                pending = find_long(cmd, long).filter(|arg| arg.is_takes_value_set());
+            }
+        } else if let Some(shorts) = word.strip_prefix('-').filter(|shorts| !shorts.is_empty()) {
+            // only `-x value`: clustered flags and `-xvalue` carry their own value
+            if let [short] = shorts.chars().collect::<Vec<_>>()[..] {
+                pending = find_short(cmd, short).filter(|arg| arg.is_takes_value_set());
+            }
+        } else if let Some(sub) = cmd
+            .find_subcommand(word)
+            .filter(|_| positionals == 0 && cmd.has_subcommands())
+        {
+            commands.push(sub);
+            path.push(sub.get_name());
+        } else {
+            positionals += 1;
+        }
+    }
+
+    let cmd = *commands.last().unwrap();
+    let candidates = if let Some(arg) = pending {
+        values(&path, arg.get_id(), current)
+    } else if let Some((long, value)) = current
+        .strip_prefix("--")
+        .and_then(|current| current.split_once('='))
+        .filter(|_| !raw)
+    {
+        match find_long(cmd, long) {
+            Some(arg) => values(&path, arg.get_id(), value)
+                .into_iter()
+                .map(|candidate| format!("--{long}={candidate}"))
+                .collect(),
+            None => Vec::new(),
+        }
+    } else if current.starts_with('-') && !raw {
+        Vec::new()
+    } else {
+        let mut args: Vec<&Arg> = cmd.get_positionals().collect();
+        args.sort_by_key(|arg| arg.get_index());
+        let arg = args.get(positionals).or_else(|| {
+            args.last()
+                .filter(|arg| arg.is_multiple_occurrences_set() || arg.is_multiple_values_set())
+        });
+        match arg {
+            Some(arg) => values(&path, arg.get_id(), current),
+            None => Vec::new(),
+        }
+    };
+
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(current))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+fn find_long<'a>(cmd: &'a Command<'static>, long: &str) -> Option<&'a Arg<'static>> {
+    cmd.get_arguments().find(|arg| arg.get_long() == Some(long))
+}
+
+fn find_short<'a>(cmd: &'a Command<'static>, short: char) -> Option<&'a Arg<'static>> {
+    cmd.get_arguments()
+        .find(|arg| arg.get_short() == Some(short))
+}
+
+/// Complete `prefix` with `paths`, one directory level at a time like the shells do.
+///
+/// Directories are returned with a trailing `/` so that completion can go on inside them.
+pub fn path_candidates(paths: &[PathBuf], prefix: &str) -> Vec<String> {
+    paths
+        .iter()
+        .map(|path| path.to_string_lossy())
+        .filter_map(|path| {
+            let rest = path.strip_prefix(prefix)?;
+            Some(match rest.find('/') {
+                Some(slash) => format!("{prefix}{}", &rest[..=slash]),
+                None => path.to_string(),
+            })
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+
+    fn command() -> Command<'static> {
+        Command::new("git-tool")
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .global(true)
+                    .takes_value(true)
+                    .possible_values(["human", "json"]),
+            )
+            .subcommand(Command::new("remove").arg(Arg::new("project-path")))
+            .subcommand(Command::new("list"))
+    }
+
+    fn values(path: &[&str], arg: &str, prefix: &str) -> Vec<String> {
+        match (path, arg) {
+            (["remove"], "project-path") => path_candidates(
+                &[PathBuf::from("vendor/lib"), PathBuf::from("vendor/app/x")],
+                prefix,
+            ),
+            _ => Vec::new(),
+        }
+    }
+
+    fn candidates(line: &str) -> Vec<String> {
+        let mut words: Vec<String> = line.split(' ').map(str::to_owned).collect();
+        let index = words.len() - 1;
+        if words[index].is_empty() {
+            words.pop();
+        }
+        complete(command(), &words, index, values)
+    }
+
+    #[test]
+    fn values_only() {
+        assert!(candidates("git-tool ").is_empty());
+        assert!(candidates("git-tool --format ").is_empty());
+        assert!(candidates("git-tool remove --f").is_empty());
+        assert_eq!(
+            candidates("git-tool --format json remove vendor/"),
+            ["vendor/app/", "vendor/lib"]
+        );
+        assert_eq!(candidates("git-tool remove --format = json v"), ["vendor/"]);
+        assert!(candidates("git-tool remove vendor/lib ").is_empty());
+    }
+
+    #[test]
+    fn scripts_run_the_hook() {
+        let bash = script(Shell::Bash, command(), "git-tool");
+        assert!(bash.contains("_git-tool() {"));
+        assert!(bash.contains("complete -F _git_tool_values -o bashdefault -o default git-tool\n"));
+        assert!(bash.contains("_git_tool() {"));
+        let zsh = script(Shell::Zsh, command(), "git-tool");
+        assert!(!zsh.contains("_git-tool \"$@\"\n"));
+        assert!(zsh.ends_with("compdef _git-tool git-tool\n"));
+        let fish = script(Shell::Fish, command(), "git-tool");
+        assert!(fish.contains("-l format -r -f -a \"{human\t,json\t}\""));
+        assert!(fish.contains("__fish_seen_subcommand_from tool"));
+    }
+}
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::{path_from_bytes, report::lossy_path, GitError, Repo};
+
+/// Lines changed in one file, as listed by [`Repo::diff_stat`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FileStat {
+    #[serde(serialize_with = "lossy_path")]
+    pub path: PathBuf,
+    /// Lines added, `None` for binary files.
+    pub insertions: Option<usize>,
+    /// Lines removed, `None` for binary files.
+    pub deletions: Option<usize>,
+}
+
+impl FileStat {
+    pub fn is_binary(&self) -> bool {
+        self.insertions.is_none()
+    }
+
+    /// Lines added and removed.
+    pub fn changes(&self) -> usize {
+        self.insertions.unwrap_or(0) + self.deletions.unwrap_or(0)
+    }
+}
+
+/// Parse the output of `git diff --numstat -z --no-renames`.
+pub fn parse_numstat(output: &[u8]) -> Result<Vec<FileStat>, String> {
+    let output = output.strip_suffix(b"\0").unwrap_or(output);
+    if output.is_empty() {
+        return Ok(Vec::new());
+    }
+    let count = |value: &[u8]| -> Result<Option<usize>, String> {
+        match value {
+            b"-" => Ok(None),
+            value => std::str::from_utf8(value)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .map(Some)
+                .ok_or_else(|| format!("invalid line count `{}`", value.escape_ascii())),
+        }
+    };
+    output
+        .split(|&byte| byte == 0)
+        .map(|entry| {
+            let mut fields = entry.trim_ascii_start().splitn(3, |&byte| byte == b'\t');
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(insertions), Some(deletions), Some(path)) => Ok(FileStat {
+                    path: path_from_bytes(path),
+                    insertions: count(insertions)?,
+                    deletions: count(deletions)?,
+                }),
+                _ => Err(format!("invalid numstat entry `{}`", entry.escape_ascii())),
+            }
+        })
+        .collect()
+}
+
+impl Repo {
+    /// Files that differ between the commits `from` and `to`, sorted by path.
+    pub fn diff_stat(&self, from: &str, to: &str) -> Result<Vec<FileStat>, GitError> {
+        self.backend().diff_stat(self, from, to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numstat() {
+        let stats = parse_numstat(b"3\t1\tsrc/lib.rs\0-\t-\tlogo.png\0").unwrap();
+        assert_eq!(
+            stats,
+            vec![
+                FileStat {
+                    path: "src/lib.rs".into(),
+                    insertions: Some(3),
+                    deletions: Some(1),
+                },
+                FileStat {
+                    path: "logo.png".into(),
+                    insertions: None,
+                    deletions: None,
+                },
+            ]
+        );
+        assert!(stats[1].is_binary());
+        assert_eq!(stats[0].changes(), 4);
+        assert!(parse_numstat(b"").unwrap().is_empty());
+        assert!(parse_numstat(b"x\t1\tfile\0").is_err());
+    }
+}
@@ -1,18 +1,54 @@
-use std::{
-    ffi::OsStr,
-    path::Path,
-    process::{Command, Stdio},
-    thread,
-    time::Duration,
-};
-pub fn run_egit<I: AsRef<OsStr>, P: AsRef<Path>>(args: Vec<I>, working_dir: P) {
-    let mut cmd = Command::new("egit")
-        .arg("--")
-        .args(args)
-        .stdin(Stdio::inherit())
-        .current_dir(working_dir)
-        .spawn()
-        .unwrap();
-    cmd.wait().unwrap();
-    thread::sleep(Duration::from_millis(10));
+use crate::{GitError, Repo};
+use std::{ffi::OsStr, path::PathBuf, process::Stdio, sync::OnceLock, thread, time::Duration};
+
+/// Binary containing egit as its `egit` subcommand, see [`use_multicall`].
+static MULTICALL: OnceLock<PathBuf> = OnceLock::new();
+
+/// Run egit as `<program> egit` instead of looking `egit` up on `PATH`.
+///
+/// The multicall `git-excess` binary calls this with its own path, so git-embed works without
+/// the `egit` link being installed.
+pub fn use_multicall(program: PathBuf) {
+    let _ = MULTICALL.set(program);
+}
+
+/// Whether egit can be run, either from the multicall binary or from `PATH`.
+pub fn is_available() -> bool {
+    MULTICALL.get().is_some() || crate::is_installed("egit")
+}
+
+impl Repo {
+    /// Run `egit` with `args` from this repository's directory.
+    ///
+    /// egit finds the `.egit` dir itself, so `GIT_DIR`/`GIT_WORK_TREE` are not forwarded.
+    pub fn run_egit<I: AsRef<OsStr>>(&self, args: Vec<I>) -> Result<(), GitError> {
+        self.run_egit_with(args, Stdio::inherit())
+    }
+
+    /// Like [`Repo::run_egit`], with egit's stdout sent to `stdout`.
+    ///
+    /// Tools printing a report on stdout pass stderr here to keep the report parseable.
+    pub fn run_egit_with<I: AsRef<OsStr>>(
+        &self,
+        args: Vec<I>,
+        stdout: Stdio,
+    ) -> Result<(), GitError> {
+        let mut cmd = match MULTICALL.get() {
+            Some(program) => {
+                let mut cmd = self.command(program);
+                cmd.arg("egit");
+                cmd
+            }
+            None => self.command("egit"),
+        };
+        cmd.env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .arg("--")
+            .args(args)
+            .stdin(Stdio::inherit())
+            .stdout(stdout);
+        self.run_status(&mut cmd)?;
+        thread::sleep(Duration::from_millis(10));
+        Ok(())
+    }
 }
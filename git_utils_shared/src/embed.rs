@@ -1,65 +1,78 @@
-use std::process::Command;
+use std::{fs, io};
 
-use itertools::Itertools;
-pub fn add_fild_to_embed_file(project: &str, fild: &str, content: &str) {
-    let _ = Command::new("git")
-        .args([
-            "config",
-            "--file",
-            ".gitembed",
-            &format!("embed.{}.{}", project, fild),
-            content,
-        ])
-        .status()
-        .expect("Failed")
-        .success();
-}
+use serde::Serialize;
 
-pub fn remove_fild_to_embed_file(project: &str, fild: &str) {
-    let _ = Command::new("git")
-        .args([
-            "config",
-            "--file",
-            ".gitembed",
-            "--unset",
-            &format!("embed.{}.{}", project, fild),
-        ])
-        .status()
-        .expect("Failed")
-        .success();
-}
+use crate::{GitError, Repo};
 
-pub fn remove_section_to_embed_file(project: &str) {
-    let _ = Command::new("git")
-        .args([
-            "config",
-            "--file",
-            ".gitembed",
-            "--remove-section",
-            &format!("embed.{project}"),
-        ])
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null())
-        .status()
-        .expect("Failed")
-        .success();
-}
+mod config;
+pub use config::{EmbedConfig, ParseError};
+
+/// The embed configuration file, relative to the repository directory.
+pub const EMBED_FILE: &str = ".gitembed";
+
+impl Repo {
+    /// Read the embed configuration file, empty when the file does not exist.
+    pub fn embed_config(&self) -> Result<EmbedConfig, GitError> {
+        let file = self.work_tree().join(EMBED_FILE);
+        let content = match fs::read(&file) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let content = String::from_utf8(content).map_err(|err| {
+            let valid = &err.as_bytes()[..err.utf8_error().valid_up_to()];
+            GitError::Config {
+                file: file.clone(),
+                line: valid.iter().filter(|&&byte| byte == b'\n').count() + 1,
+                message: "not valid UTF-8".to_string(),
+            }
+        })?;
+        EmbedConfig::parse(&content).map_err(|err| GitError::Config {
+            file,
+            line: err.line,
+            message: err.message,
+        })
+    }
+
+    pub fn write_embed_config(&self, config: &EmbedConfig) -> Result<(), GitError> {
+        fs::write(self.work_tree().join(EMBED_FILE), config.to_string())?;
+        Ok(())
+    }
+
+    pub fn add_fild_to_embed_file(
+        &self,
+        project: &str,
+        fild: &str,
+        content: &str,
+    ) -> Result<(), GitError> {
+        let mut config = self.embed_config()?;
+        config.set(project, fild, content);
+        self.write_embed_config(&config)
+    }
 
-pub fn get_head_of_embed_project(embed_project_path: &str) -> String {
-    let stdout_raw = Command::new("git")
-        .args([
-            "--git-dir",
-            &format!("{}/.egit", embed_project_path),
-            "rev-parse",
-            "HEAD",
-        ])
-        .output()
-        .expect("Failed")
-        .stdout;
-    String::from_utf8(stdout_raw).unwrap().trim().to_string()
+    pub fn remove_fild_to_embed_file(&self, project: &str, fild: &str) -> Result<(), GitError> {
+        let mut config = self.embed_config()?;
+        if config.unset(project, fild) {
+            self.write_embed_config(&config)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the whole `embed.<project>` section. A missing section is not an error.
+    pub fn remove_section_to_embed_file(&self, project: &str) -> Result<(), GitError> {
+        let mut config = self.embed_config()?;
+        if config.remove(project) {
+            self.write_embed_config(&config)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_head_of_embed_project(&self, embed_project_path: &str) -> Result<String, GitError> {
+        self.embed(embed_project_path).get_head()
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct EmbedEnty {
     pub name: String,
     pub git_url: String,
@@ -91,46 +104,14 @@ impl tabled::Tabled for EmbedEnty {
         ]
     }
 }
-pub fn get_embeds() -> Vec<EmbedEnty> {
-    let stdout_raw = Command::new("git")
-        .args(["config", "--file", ".gitembed", "--list"])
-        .output()
-        .expect("Failed")
-        .stdout;
-    let stdout = String::from_utf8(stdout_raw).unwrap().trim().to_string();
-
-    let mut embeds = Vec::new();
-
-    for (url_line, path_line, head_line) in stdout.lines().tuples() {
-        let get_value = |line: &str| -> String {
-            line.split("=")
-                .collect::<Vec<&str>>()
-                .last()
-                .unwrap()
-                .to_string()
-        };
-
-        let git_url = get_value(url_line);
-        let path = get_value(path_line);
-        let head = get_value(head_line);
-
-        let name = path_line
-            .split("=")
-            .collect::<Vec<&str>>()
-            .first()
-            .unwrap()
-            .split(".")
-            .collect::<Vec<&str>>()
-            .get(1)
-            .unwrap()
-            .to_string();
-
-        embeds.push(EmbedEnty {
-            name,
-            git_url,
-            path,
-            head,
-        });
+impl Repo {
+    pub fn get_embeds(&self) -> Result<Vec<EmbedEnty>, GitError> {
+        self.embed_config()?
+            .embeds()
+            .map_err(|err| GitError::Config {
+                file: self.work_tree().join(EMBED_FILE),
+                line: err.line,
+                message: err.message,
+            })
     }
-    embeds
 }
@@ -0,0 +1,715 @@
+use std::fmt;
+
+use super::EmbedEnty;
+
+/// Section name used for embedded projects: `[embed "<name>"]`.
+const SECTION: &str = "embed";
+
+/// Error found while reading a `.gitembed` file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line number the error was found on.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Debug)]
+enum LineKind {
+    /// Blank line, comment, or anything else written back untouched.
+    Other,
+    /// `[section "subsection"]` or the legacy `[section.subsection]`.
+    Section {
+        name: String,
+        subsection: Option<String>,
+    },
+    /// `key = value` with an optional trailing comment kept as written.
+    Entry {
+        key: String,
+        value: String,
+        comment: String,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Line {
+    text: String,
+    kind: LineKind,
+    /// Entry written on the line of the section header before it: `[section] key = value`.
+    joined: bool,
+    /// `\n`, `\r\n`, or nothing for a header followed by an entry and a last line without one.
+    ending: String,
+}
+
+/// The `.gitembed` file, in git-config syntax.
+///
+/// Lines are kept as written so that comments, ordering and unknown keys or sections survive
+/// editing; only the lines that are set or removed change.
+#[derive(Clone, Debug, Default)]
+pub struct EmbedConfig {
+    lines: Vec<Line>,
+}
+
+impl EmbedConfig {
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut lines = Vec::new();
+        let mut in_section = false;
+        for (index, raw) in input.split_inclusive('\n').enumerate() {
+            let text = raw
+                .strip_suffix('\n')
+                .map_or(raw, |text| text.strip_suffix('\r').unwrap_or(text));
+            let ending = &raw[text.len()..];
+            let error = |message: String| ParseError {
+                line: index + 1,
+                message,
+            };
+            let (kind, rest) = parse_line(text).map_err(error)?;
+            match kind {
+                LineKind::Section { .. } => in_section = true,
+                LineKind::Entry { ref key, .. } if !in_section => {
+                    return Err(error(format!("`{key}` is not inside a section")));
+                }
+                _ => {}
+            }
+            let header_end = text.len() - rest.len();
+            lines.push(Line {
+                text: text[..header_end].to_string(),
+                kind,
+                joined: false,
+                ending: if rest.is_empty() { ending } else { "" }.to_string(),
+            });
+            if !rest.is_empty() {
+                lines.push(Line {
+                    text: rest.to_string(),
+                    kind: parse_entry(rest.trim_start()).map_err(error)?,
+                    joined: true,
+                    ending: ending.to_string(),
+                });
+            }
+        }
+        Ok(EmbedConfig { lines })
+    }
+
+    /// Value of `key` for the embedded project `name`; the last one wins like in git.
+    pub fn get(&self, name: &str, key: &str) -> Option<&str> {
+        let mut found = None;
+        let mut current = false;
+        for line in &self.lines {
+            match &line.kind {
+                LineKind::Section { .. } => current = is_embed(&line.kind, name),
+                LineKind::Entry {
+                    key: entry_key,
+                    value,
+                    ..
+                } if current && entry_key.eq_ignore_ascii_case(key) => found = Some(value.as_str()),
+                _ => {}
+            }
+        }
+        found
+    }
+
+    /// Every entry as `(section.subsection.key, value)` in file order, named like `git config
+    /// --list` does: section and key lowercased, the subsection as written.
+    pub fn entries(&self) -> Vec<(String, &str)> {
+        let mut section = String::new();
+        let mut entries = Vec::new();
+        for line in &self.lines {
+            match &line.kind {
+                LineKind::Section { name, subsection } => {
+                    section = name.to_ascii_lowercase();
+                    if let Some(subsection) = subsection {
+                        section = format!("{section}.{subsection}");
+                    }
+                }
+                LineKind::Entry { key, value, .. } => entries.push((
+                    format!("{section}.{}", key.to_ascii_lowercase()),
+                    value.as_str(),
+                )),
+                LineKind::Other => {}
+            }
+        }
+        entries
+    }
+
+    /// Set `key` of the embedded project `name`, adding the entry or section when missing.
+    pub fn set(&mut self, name: &str, key: &str, value: &str) {
+        let mut current = false;
+        let mut existing = None;
+        let mut section_end = None;
+        for (index, line) in self.lines.iter().enumerate() {
+            match &line.kind {
+                LineKind::Section { .. } => {
+                    current = is_embed(&line.kind, name);
+                    if current {
+                        section_end = Some(index);
+                    }
+                }
+                LineKind::Entry { key: entry_key, .. } if current => {
+                    section_end = Some(index);
+                    if entry_key.eq_ignore_ascii_case(key) {
+                        existing = Some(index);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(index) = existing {
+            let line = &mut self.lines[index];
+            let indent: String = line
+                .text
+                .chars()
+                .take_while(|c| c.is_whitespace())
+                .collect();
+            if let LineKind::Entry {
+                key: entry_key,
+                value: entry_value,
+                comment,
+            } = &mut line.kind
+            {
+                line.text = format!("{indent}{entry_key} = {}{comment}", quote(value));
+                *entry_value = value.to_string();
+            }
+            return;
+        }
+
+        let entry = |indent: &str| {
+            let kind = LineKind::Entry {
+                key: key.to_string(),
+                value: value.to_string(),
+                comment: String::new(),
+            };
+            (format!("{indent}{key} = {}", quote(value)), kind)
+        };
+        match section_end {
+            Some(index) => {
+                let indent = match self.lines[index].kind {
+                    LineKind::Entry { .. } if !self.lines[index].joined => self.lines[index]
+                        .text
+                        .chars()
+                        .take_while(|c| c.is_whitespace())
+                        .collect(),
+                    _ => "\t".to_string(),
+                };
+                let (text, kind) = entry(&indent);
+                self.insert(index + 1, text, kind);
+            }
+            None => {
+                let end = self.lines.len();
+                let section = LineKind::Section {
+                    name: SECTION.to_string(),
+                    subsection: Some(name.to_string()),
+                };
+                self.insert(
+                    end,
+                    format!("[{SECTION} \"{}\"]", escape_subsection(name)),
+                    section,
+                );
+                let (text, kind) = entry("\t");
+                self.insert(end + 1, text, kind);
+            }
+        }
+    }
+
+    /// Insert a line before `index`, ended like the other lines of the file.
+    fn insert(&mut self, index: usize, text: String, kind: LineKind) {
+        let newline = self
+            .lines
+            .iter()
+            .map(|line| line.ending.as_str())
+            .find(|ending| !ending.is_empty())
+            .unwrap_or("\n")
+            .to_string();
+        let mut ending = newline.clone();
+        // a file without a final newline stays without one
+        if index == self.lines.len() {
+            if let Some(last) = self.lines.last_mut().filter(|last| last.ending.is_empty()) {
+                last.ending = newline;
+                ending = String::new();
+            }
+        }
+        self.lines.insert(
+            index,
+            Line {
+                text,
+                kind,
+                joined: false,
+                ending,
+            },
+        );
+    }
+
+    /// Remove the lines `removed` marks, keeping the line endings of the file as they were.
+    /// Returns whether anything was removed.
+    fn remove_lines(&mut self, removed: &[bool]) -> bool {
+        let before = self.lines.len();
+        for index in (0..before).rev().filter(|&index| removed[index]) {
+            let line = self.lines.remove(index);
+            let last = index == self.lines.len();
+            if let Some(previous) = index.checked_sub(1).map(|index| &mut self.lines[index]) {
+                // a header ends where the entry on its line ended, and so does a new last line
+                if previous.ending.is_empty() || (last && line.ending.is_empty()) {
+                    previous.ending = line.ending;
+                }
+            }
+        }
+        self.lines.len() != before
+    }
+
+    /// Remove `key` from the embedded project `name`. Returns whether anything was removed.
+    pub fn unset(&mut self, name: &str, key: &str) -> bool {
+        let mut current = false;
+        let removed: Vec<bool> = self
+            .lines
+            .iter()
+            .map(|line| match &line.kind {
+                LineKind::Section { .. } => {
+                    current = is_embed(&line.kind, name);
+                    false
+                }
+                LineKind::Entry { key: entry_key, .. } => {
+                    current && entry_key.eq_ignore_ascii_case(key)
+                }
+                LineKind::Other => false,
+            })
+            .collect();
+        self.remove_lines(&removed)
+    }
+
+    /// Remove every section of the embedded project `name`, comments inside it included.
+    /// Returns whether anything was removed.
+    ///
+    /// Comments right above the header of another section are kept, they describe that one.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let mut current = false;
+        let removed: Vec<bool> = (0..self.lines.len())
+            .map(|index| {
+                let line = &self.lines[index];
+                if let LineKind::Section { .. } = line.kind {
+                    current = is_embed(&line.kind, name);
+                }
+                // the comment block ends at a blank line or the header it describes
+                let comment_of = self.lines[index..]
+                    .iter()
+                    .find(|line| !matches!(line.kind, LineKind::Other) || is_blank(line));
+                let describes_other_section = matches!(line.kind, LineKind::Other)
+                    && !is_blank(line)
+                    && comment_of.is_some_and(|next| {
+                        matches!(next.kind, LineKind::Section { .. }) && !is_embed(&next.kind, name)
+                    });
+                current && !describes_other_section
+            })
+            .collect();
+        self.remove_lines(&removed)
+    }
+
+    /// The embedded projects in file order, each checked for `url`, `path` and `head`.
+    ///
+    /// Several sections with the same name are merged like git does, unknown keys are ignored.
+    pub fn embeds(&self) -> Result<Vec<EmbedEnty>, ParseError> {
+        // (name, line of the first section header)
+        let mut names: Vec<(&str, usize)> = Vec::new();
+        for (index, line) in self.lines.iter().enumerate() {
+            if let LineKind::Section { name, subsection } = &line.kind {
+                if !name.eq_ignore_ascii_case(SECTION) {
+                    continue;
+                }
+                match subsection {
+                    Some(subsection) => {
+                        if !names.iter().any(|(seen, _)| seen == subsection) {
+                            names.push((subsection, index + 1));
+                        }
+                    }
+                    None => {
+                        return Err(ParseError {
+                            line: index + 1,
+                            message: format!("[{name}] section needs a project name"),
+                        })
+                    }
+                }
+            }
+        }
+
+        names
+            .into_iter()
+            .map(|(name, line)| {
+                let value = |key: &str| {
+                    self.get(name, key)
+                        .map(str::to_string)
+                        .ok_or_else(|| ParseError {
+                            line,
+                            message: format!("embed \"{name}\" has no `{key}`"),
+                        })
+                };
+                Ok(EmbedEnty {
+                    name: name.to_string(),
+                    git_url: value("url")?,
+                    path: value("path")?,
+                    head: value("head")?,
+                })
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for EmbedConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            f.write_str(&line.text)?;
+            f.write_str(&line.ending)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_blank(line: &Line) -> bool {
+    line.text.trim().is_empty()
+}
+
+fn is_embed(kind: &LineKind, project: &str) -> bool {
+    matches!(kind, LineKind::Section { name, subsection: Some(subsection) }
+        if name.eq_ignore_ascii_case(SECTION) && subsection == project)
+}
+
+/// Parse one line, with the rest of it when an entry follows a section header on the same line.
+fn parse_line(text: &str) -> Result<(LineKind, &str), String> {
+    let trimmed = text.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+        Ok((LineKind::Other, ""))
+    } else if let Some(header) = trimmed.strip_prefix('[') {
+        parse_section(header)
+    } else {
+        Ok((parse_entry(trimmed)?, ""))
+    }
+}
+
+/// Parse what follows the `[` of a section header, returning the rest of the line when it holds
+/// an entry.
+fn parse_section(header: &str) -> Result<(LineKind, &str), String> {
+    let (inside, rest) = match header.find(['"', ']']) {
+        Some(end) if header.as_bytes()[end] == b'"' => {
+            let name = header[..end].trim_end();
+            if name.is_empty() || name.len() == header[..end].len() {
+                return Err("expected a space between section and subsection".to_string());
+            }
+            let (subsection, rest) = parse_quoted(&header[end + 1..])?;
+            let rest = rest
+                .strip_prefix(']')
+                .ok_or("expected `]` after the subsection")?;
+            check_name(name)?;
+            let kind = LineKind::Section {
+                name: name.to_string(),
+                subsection: Some(subsection),
+            };
+            return Ok((kind, end_of_header(rest)));
+        }
+        Some(end) => (&header[..end], &header[end + 1..]),
+        None => return Err("unterminated section header".to_string()),
+    };
+    // legacy `[section.subsection]`, lowercased as a whole like git does
+    let inside = inside.to_ascii_lowercase();
+    let (name, subsection) = match inside.split_once('.') {
+        Some((name, subsection)) => (name, Some(subsection.to_string())),
+        None => (inside.as_str(), None),
+    };
+    check_name(name)?;
+    let kind = LineKind::Section {
+        name: name.to_string(),
+        subsection,
+    };
+    Ok((kind, end_of_header(rest)))
+}
+
+/// The entry following a section header on the same line, empty when there is none.
+fn end_of_header(rest: &str) -> &str {
+    let trimmed = rest.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+        ""
+    } else {
+        rest
+    }
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+    {
+        return Err(format!("invalid section name `{name}`"));
+    }
+    Ok(())
+}
+
+/// Parse a quoted subsection name after its opening quote, returning it and the rest of the line.
+fn parse_quoted(input: &str) -> Result<(String, &str), String> {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &input[index + 1..])),
+            '\\' => match chars.next() {
+                Some((_, escaped)) => value.push(escaped),
+                None => break,
+            },
+            _ => value.push(c),
+        }
+    }
+    Err("unterminated subsection name".to_string())
+}
+
+fn parse_entry(text: &str) -> Result<LineKind, String> {
+    let key_end = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .unwrap_or(text.len());
+    let key = &text[..key_end];
+    if key.is_empty() || !key.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err(format!("invalid key in `{text}`"));
+    }
+
+    let rest = text[key_end..].trim_start();
+    let (value, comment) = match rest.strip_prefix('=') {
+        Some(value) => parse_value(value)?,
+        // a key without `=` is a boolean set to true
+        None if rest.is_empty() || rest.starts_with('#') || rest.starts_with(';') => {
+            ("true".to_string(), text[key_end..].to_string())
+        }
+        None => return Err(format!("expected `=` after `{key}`")),
+    };
+    Ok(LineKind::Entry {
+        key: key.to_string(),
+        value,
+        comment,
+    })
+}
+
+/// Parse a value the way git does, returning it and the trailing comment as written.
+fn parse_value(input: &str) -> Result<(String, String), String> {
+    let mut value = String::new();
+    let mut quoted = false;
+    // length of `value` up to the last character that is not unquoted whitespace
+    let mut kept = 0;
+    let mut chars = input.char_indices().peekable();
+    // skip leading whitespace
+    while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '#' | ';' if !quoted => {
+                value.truncate(kept);
+                let comment_start = input[..index].trim_end().len();
+                return Ok((value, input[comment_start..].to_string()));
+            }
+            '"' => quoted = !quoted,
+            '\\' => {
+                let escaped = match chars.next() {
+                    Some((_, 'n')) => '\n',
+                    Some((_, 't')) => '\t',
+                    Some((_, 'b')) => '\u{8}',
+                    Some((_, c @ ('"' | '\\'))) => c,
+                    Some((_, c)) => return Err(format!("invalid escape `\\{c}`")),
+                    None => return Err("line continuations are not supported".to_string()),
+                };
+                value.push(escaped);
+                kept = value.len();
+            }
+            c => {
+                value.push(c);
+                if quoted || !c.is_whitespace() {
+                    kept = value.len();
+                }
+            }
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted value".to_string());
+    }
+    value.truncate(kept);
+    Ok((value, String::new()))
+}
+
+/// Write a value so that [`parse_value`] reads it back unchanged.
+fn quote(value: &str) -> String {
+    let needs_quotes = value.starts_with(char::is_whitespace)
+        || value.ends_with(char::is_whitespace)
+        || value.contains(['#', ';']);
+    let mut quoted = String::with_capacity(value.len() + 2);
+    if needs_quotes {
+        quoted.push('"');
+    }
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\u{8}' => quoted.push_str("\\b"),
+            c => quoted.push(c),
+        }
+    }
+    if needs_quotes {
+        quoted.push('"');
+    }
+    quoted
+}
+
+fn escape_subsection(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "\
+# embedded projects
+[embed \"vendor/lib.v2\"]
+\tpath = vendor/lib.v2
+\turl = https://example.com/lib.git?ref=v2 ; pinned
+\thead = 1234abcd
+\towner = someone
+
+[core]
+\tautocrlf = false
+[embed \"tools\"]
+    url = \"git@example.com:tools.git\"
+    path = tools
+    head = 5678ef00
+";
+
+    #[test]
+    fn round_trip() {
+        let config = EmbedConfig::parse(FILE).unwrap();
+        assert_eq!(config.to_string(), FILE);
+
+        let embeds = config.embeds().unwrap();
+        assert_eq!(embeds.len(), 2);
+        assert_eq!(embeds[0].name, "vendor/lib.v2");
+        assert_eq!(embeds[0].git_url, "https://example.com/lib.git?ref=v2");
+        assert_eq!(embeds[0].path, "vendor/lib.v2");
+        assert_eq!(embeds[1].git_url, "git@example.com:tools.git");
+        assert_eq!(embeds[1].head, "5678ef00");
+    }
+
+    #[test]
+    fn edit() {
+        let mut config = EmbedConfig::parse(FILE).unwrap();
+        config.set("vendor/lib.v2", "url", "https://example.com/lib#v3");
+        config.set("tools", "branch", "main");
+        config.set("new", "path", "new dir ");
+        assert!(config.unset("vendor/lib.v2", "owner"));
+        assert!(!config.unset("vendor/lib.v2", "owner"));
+
+        let written = config.to_string();
+        assert!(written.starts_with("# embedded projects\n"));
+        assert!(written.contains("\turl = \"https://example.com/lib#v3\" ; pinned\n"));
+        assert!(written.contains("    head = 5678ef00\n    branch = main\n"));
+        assert!(written.ends_with("[embed \"new\"]\n\tpath = \"new dir \"\n"));
+        assert!(!written.contains("owner"));
+
+        let reread = EmbedConfig::parse(&written).unwrap();
+        assert_eq!(
+            reread.get("vendor/lib.v2", "url"),
+            Some("https://example.com/lib#v3")
+        );
+        assert_eq!(reread.get("new", "path"), Some("new dir "));
+
+        assert!(config.remove("tools"));
+        assert!(config
+            .to_string()
+            .contains("[core]\n\tautocrlf = false\n[embed \"new\"]"));
+    }
+
+    #[test]
+    fn remove_keeps_next_section_comments() {
+        let mut config = EmbedConfig::parse(
+            "[embed \"a\"]\n\turl = a\n\t# old mirror\n\n# the tools\n[embed \"b\"]\n\turl = b\n# end\n",
+        )
+        .unwrap();
+        assert!(config.remove("a"));
+        assert_eq!(
+            config.to_string(),
+            "# the tools\n[embed \"b\"]\n\turl = b\n# end\n"
+        );
+        assert!(config.remove("b"));
+        assert_eq!(config.to_string(), "# the tools\n");
+    }
+
+    #[test]
+    fn legacy_header_is_lowercased() {
+        let config = EmbedConfig::parse("[Embed.Lib]\n\turl = x\n").unwrap();
+        assert_eq!(config.get("lib", "url"), Some("x"));
+        assert_eq!(config.get("Lib", "url"), None);
+        assert_eq!(config.entries(), [("embed.lib.url".to_string(), "x")]);
+    }
+
+    #[test]
+    fn entry_on_the_header_line() {
+        let input = "[embed \"a\"] url = x ; pinned\n\tpath = a\n[core]autocrlf\n";
+        let mut config = EmbedConfig::parse(input).unwrap();
+        assert_eq!(config.to_string(), input);
+        assert_eq!(config.get("a", "url"), Some("x"));
+        assert_eq!(config.entries()[2], ("core.autocrlf".to_string(), "true"));
+
+        config.set("a", "url", "y");
+        assert!(config
+            .to_string()
+            .starts_with("[embed \"a\"] url = y ; pinned\n"));
+        assert!(config.unset("a", "url"));
+        assert!(config
+            .to_string()
+            .starts_with("[embed \"a\"]\n\tpath = a\n"));
+        assert!(config.remove("a"));
+        assert_eq!(config.to_string(), "[core]autocrlf\n");
+    }
+
+    #[test]
+    fn line_endings_round_trip() {
+        let input =
+            "# projects\r\n[embed \"a\"] url = x\r\n\tpath = a\r\n[embed \"b\"]\r\n\turl = y";
+        let mut config = EmbedConfig::parse(input).unwrap();
+        assert_eq!(config.to_string(), input);
+        assert_eq!(config.get("b", "url"), Some("y"));
+
+        config.set("b", "url", "z");
+        config.set("b", "head", "1234");
+        config.set("c", "url", "w");
+        assert!(config.unset("a", "url"));
+        assert_eq!(
+            config.to_string(),
+            "# projects\r\n[embed \"a\"]\r\n\tpath = a\r\n[embed \"b\"]\r\n\turl = z\r\n\thead = 1234\r\n[embed \"c\"]\r\n\turl = w"
+        );
+        assert!(config.remove("c"));
+        assert!(config.remove("a"));
+        assert_eq!(
+            config.to_string(),
+            "# projects\r\n[embed \"b\"]\r\n\turl = z\r\n\thead = 1234"
+        );
+
+        let unix = EmbedConfig::parse("[embed \"a\"]\n\turl = x\n").unwrap();
+        assert_eq!(unix.to_string(), "[embed \"a\"]\n\turl = x\n");
+    }
+
+    #[test]
+    fn errors() {
+        let error = |input: &str| EmbedConfig::parse(input).unwrap_err().line;
+        assert_eq!(error("[embed \"a\"]\n\turl = x\n[embed \"b\"\n"), 3);
+        assert_eq!(error("url = x\n"), 1);
+        assert_eq!(error("[embed \"a\"]\n\turl = \"x\n"), 2);
+        assert_eq!(error("[embed \"a\"]\n\t= x\n"), 2);
+
+        let missing = EmbedConfig::parse("\n[embed \"a\"]\n\turl = x\n\tpath = a\n")
+            .unwrap()
+            .embeds()
+            .unwrap_err();
+        assert_eq!(missing.line, 2);
+        assert_eq!(missing.message, "embed \"a\" has no `head`");
+    }
+}
@@ -0,0 +1,152 @@
+use std::{fmt, io, path::PathBuf, process::Command, string::FromUtf8Error};
+
+/// Error returned by every helper of this crate that runs a git command.
+#[derive(Debug)]
+pub enum GitError {
+    /// The command could not be started at all (e.g. `git` is not on PATH).
+    Spawn { command: String, source: io::Error },
+    /// The command ran but exited unsuccessfully.
+    Failed {
+        command: String,
+        status: Option<i32>,
+        stderr: String,
+    },
+    /// The command output was not valid UTF-8.
+    NonUtf8 {
+        command: String,
+        source: FromUtf8Error,
+    },
+    /// The command output could not be understood.
+    Parse { command: String, message: String },
+    /// Files that must be committed before the operation have uncommitted changes.
+    Dirty { paths: Vec<PathBuf> },
+    /// A configuration file the tools read directly is malformed.
+    Config {
+        file: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// An `excess.*` setting has a value the tool does not understand.
+    Setting {
+        key: String,
+        value: String,
+        /// Where the value was set, as listed by `git excess config --list`.
+        origin: String,
+        message: String,
+    },
+    /// An in-process backend could not carry out an operation.
+    Backend { operation: String, message: String },
+    /// A filesystem operation done alongside a git command failed.
+    Io(io::Error),
+}
+
+impl GitError {
+    /// Exit code a tool should use when it gives up because of this error.
+    ///
+    /// Failed commands forward git's own exit code, a missing program maps to 127 like a shell does.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            GitError::Spawn { .. } => 127,
+            GitError::Failed {
+                status: Some(code), ..
+            } if *code != 0 => *code,
+            _ => 1,
+        }
+    }
+
+    pub(crate) fn spawn(cmd: &Command, source: io::Error) -> Self {
+        GitError::Spawn {
+            command: command_line(cmd),
+            source,
+        }
+    }
+
+    pub(crate) fn failed(cmd: &Command, status: Option<i32>, stderr: &[u8]) -> Self {
+        GitError::Failed {
+            command: command_line(cmd),
+            status,
+            stderr: String::from_utf8_lossy(stderr).trim().to_string(),
+        }
+    }
+
+    pub(crate) fn parse(cmd: &Command, message: impl Into<String>) -> Self {
+        GitError::Parse {
+            command: command_line(cmd),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for GitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitError::Spawn { command, source } => write!(f, "could not run `{command}`: {source}"),
+            GitError::Failed {
+                command,
+                status,
+                stderr,
+            } => {
+                match status {
+                    Some(code) => write!(f, "`{command}` exited with status {code}")?,
+                    None => write!(f, "`{command}` was terminated by a signal")?,
+                }
+                if !stderr.is_empty() {
+                    write!(f, "\n{stderr}")?;
+                }
+                Ok(())
+            }
+            GitError::NonUtf8 { command, .. } => {
+                write!(f, "`{command}` produced output that is not valid UTF-8")
+            }
+            GitError::Parse { command, message } => {
+                write!(f, "could not parse output of `{command}`: {message}")
+            }
+            GitError::Dirty { paths } => {
+                let paths: Vec<String> = paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect();
+                write!(f, "{} is dirty, please commit it first", paths.join(", "))
+            }
+            GitError::Config {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", file.display()),
+            GitError::Setting {
+                key,
+                value,
+                origin,
+                message,
+            } => write!(f, "bad value `{value}` for {key} ({origin}): {message}"),
+            GitError::Backend { operation, message } => write!(f, "git {operation}: {message}"),
+            GitError::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for GitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GitError::Spawn { source, .. } => Some(source),
+            GitError::NonUtf8 { source, .. } => Some(source),
+            GitError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GitError {
+    fn from(err: io::Error) -> Self {
+        GitError::Io(err)
+    }
+}
+
+/// Render a command the way a user would type it in a shell.
+pub(crate) fn command_line(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
@@ -1,77 +1,367 @@
-#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{path_from_bytes, report::lossy_path, GitError, Repo};
+
+/// One entry of `git status --porcelain=v2`.
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Serialize)]
 pub struct GitStatusFile {
-    name: String,
-    status: GitStatus,
+    #[serde(serialize_with = "lossy_path")]
+    pub path: PathBuf,
+    /// State of the file in the index compared to HEAD.
+    pub index: GitStatus,
+    /// State of the file in the work tree compared to the index.
+    pub worktree: GitStatus,
+    /// Set when the entry is a submodule.
+    pub submodule: Option<SubmoduleStatus>,
+    /// Where a renamed or copied file comes from.
+    pub source: Option<RenameSource>,
+    /// Object names of the conflict stages of an unmerged file.
+    pub conflict: Option<ConflictStages>,
 }
-#[derive(Hash, Eq, PartialEq, Clone, Debug)]
+
+/// State of one side of a status entry, the `X` or `Y` of `XY`.
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum GitStatus {
-    Renamed,
+    Unmodified,
     Modified,
-    Deleted,
+    TypeChanged,
     Added,
+    Deleted,
+    Renamed,
+    Copied,
+    /// Updated but unmerged
+    Unmerged,
     Untracked,
+    Ignored,
 }
-use std::{collections::HashMap, process::Command};
 
-pub fn git_status() -> HashMap<GitStatus, GitStatusFile> {
-    let cmd = Command::new("git")
-        .args(["status", "--porcelain=v2", "-s"])
-        .output()
-        .expect("Failed ")
-        .stdout;
-    let stdout = String::from_utf8(cmd).unwrap();
-    let mut git_status_record = HashMap::new();
-    for line in stdout.split("\n") {
-        let line_split: Vec<&str> = line.trim().split(" ").filter(|c| !c.is_empty()).collect();
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug, Serialize)]
+pub struct SubmoduleStatus {
+    pub commit_changed: bool,
+    pub modified: bool,
+    pub untracked: bool,
+}
+
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Serialize)]
+pub struct RenameSource {
+    #[serde(serialize_with = "lossy_path")]
+    pub path: PathBuf,
+    /// Similarity between source and destination, in percent.
+    pub score: u8,
+}
+
+/// Object names of the stages of an unmerged file, `None` when the stage is absent.
+#[derive(Hash, Eq, PartialEq, Clone, Debug, Serialize)]
+pub struct ConflictStages {
+    pub base: Option<String>,
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+}
+
+impl GitStatusFile {
+    fn new(path: &[u8], index: GitStatus, worktree: GitStatus) -> Self {
+        GitStatusFile {
+            path: path_from_bytes(path),
+            index,
+            worktree,
+            submodule: None,
+            source: None,
+            conflict: None,
+        }
+    }
 
-        dbg!(&line_split);
+    /// The file has changes staged in the index.
+    pub fn is_staged(&self) -> bool {
+        !matches!(
+            self.index,
+            GitStatus::Unmodified | GitStatus::Untracked | GitStatus::Ignored | GitStatus::Unmerged
+        )
+    }
 
+    /// The file has a merge conflict.
+    pub fn is_unmerged(&self) -> bool {
+        self.conflict.is_some()
+    }
+}
+
+impl GitStatus {
+    fn from_code(code: char) -> Option<Self> {
+        use GitStatus::*;
+        Some(match code {
+            '.' => Unmodified,
+            'M' => Modified,
+            'T' => TypeChanged,
+            'A' => Added,
+            'D' => Deleted,
+            'R' => Renamed,
+            'C' => Copied,
+            'U' => Unmerged,
+            '?' => Untracked,
+            '!' => Ignored,
+            _ => return None,
+        })
+    }
+
+    /// The letter porcelain v2 uses for this state, `.` when unmodified.
+    pub fn code(self) -> char {
         use GitStatus::*;
-        let status = match line_split[0] {
-            "AD" | "D" => Deleted,
-            "AM" | "M" => Modified,
-            "AR" | "R" => Renamed,
-            "A" => Added,
-            "??" => Untracked,
-            err => panic!("{} is not an implemented git status", err),
+        match self {
+            Unmodified => '.',
+            Modified => 'M',
+            TypeChanged => 'T',
+            Added => 'A',
+            Deleted => 'D',
+            Renamed => 'R',
+            Copied => 'C',
+            Unmerged => 'U',
+            Untracked => '?',
+            Ignored => '!',
+        }
+    }
+}
+
+/// Parse the NUL delimited output of `git status --porcelain=v2 -z`.
+///
+/// Header lines (`# branch.oid ...`) are skipped. Paths are kept byte for byte.
+pub fn parse_porcelain_v2(output: &[u8]) -> Result<Vec<GitStatusFile>, String> {
+    let mut entries = Vec::new();
+    let output = output.strip_suffix(b"\0").unwrap_or(output);
+    let mut records = output
+        .split(|&byte| byte == 0)
+        .filter(|record| !record.is_empty());
+
+    while let Some(record) = records.next() {
+        let (kind, rest) = record.split_at(record.len().min(2));
+        let entry = match kind {
+            b"# " => continue,
+            b"? " => GitStatusFile::new(rest, GitStatus::Untracked, GitStatus::Untracked),
+            b"! " => GitStatusFile::new(rest, GitStatus::Ignored, GitStatus::Ignored),
+            b"1 " => {
+                // <XY> <sub> <mH> <mI> <mW> <hH> <hI> <path>
+                let (fields, path) = fields(record, rest, 8)?;
+                changed_entry(record, fields[0], fields[1], path)?
+            }
+            b"2 " => {
+                // <XY> <sub> <mH> <mI> <mW> <hH> <hI> <X><score> <path>, then <origPath>
+                let (fields, path) = fields(record, rest, 9)?;
+                let mut entry = changed_entry(record, fields[0], fields[1], path)?;
+                let score = fields[7]
+                    .get(1..)
+                    .and_then(|score| score.parse().ok())
+                    .ok_or_else(|| format!("invalid rename score in `{}`", lossy(record)))?;
+                let source = records
+                    .next()
+                    .ok_or_else(|| format!("missing rename source for `{}`", lossy(record)))?;
+                entry.source = Some(RenameSource {
+                    path: path_from_bytes(source),
+                    score,
+                });
+                entry
+            }
+            b"u " => {
+                // <XY> <sub> <m1> <m2> <m3> <mW> <h1> <h2> <h3> <path>
+                let (fields, path) = fields(record, rest, 10)?;
+                let mut entry = changed_entry(record, fields[0], fields[1], path)?;
+                let stage = |hash: &str| {
+                    if hash.bytes().all(|b| b == b'0') {
+                        None
+                    } else {
+                        Some(hash.to_string())
+                    }
+                };
+                entry.conflict = Some(ConflictStages {
+                    base: stage(fields[6]),
+                    ours: stage(fields[7]),
+                    theirs: stage(fields[8]),
+                });
+                entry
+            }
+            _ => return Err(format!("unknown status entry `{}`", lossy(record))),
         };
+        entries.push(entry);
+    }
+    Ok(entries)
+}
 
-        git_status_record.insert(
-            status.clone(),
-            GitStatusFile {
-                name: line_split[1].to_string(),
-                status: status,
-            },
-        );
+fn lossy(record: &[u8]) -> std::borrow::Cow<'_, str> {
+    String::from_utf8_lossy(record)
+}
+
+/// Split the fields after the entry type into the text ones and the path, which comes last and
+/// may contain spaces or any other byte.
+fn fields<'a>(
+    record: &[u8],
+    rest: &'a [u8],
+    count: usize,
+) -> Result<(Vec<&'a str>, &'a [u8]), String> {
+    let mut fields: Vec<&[u8]> = rest.splitn(count, |&byte| byte == b' ').collect();
+    if fields.len() != count {
+        return Err(format!("truncated status entry `{}`", lossy(record)));
     }
-    git_status_record
+    let path = fields.pop().unwrap_or_default();
+    let fields = fields
+        .into_iter()
+        .map(std::str::from_utf8)
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("invalid status entry `{}`", lossy(record)))?;
+    Ok((fields, path))
 }
-pub fn modified_files(file_paths: &Vec<String>) -> Option<Vec<String>> {
-    let stdout_raw = Command::new("git")
-        .args(["--no-pager", "diff", "--name-only", "-z"])
-        .args(file_paths)
-        .output()
-        .expect("Failed ")
-        .stdout;
-    let stdout_str = String::from_utf8(stdout_raw).unwrap();
-
-    if stdout_str.is_empty() {
-        return None;
-    }
-    let files = stdout_str[..stdout_str.len() - 1]
-        .split("\0")
-        .map(|path| path.to_owned())
-        .collect::<Vec<String>>();
-    Some(files)
+
+fn changed_entry(record: &[u8], xy: &str, sub: &str, path: &[u8]) -> Result<GitStatusFile, String> {
+    let mut codes = xy.chars().map(GitStatus::from_code);
+    match (codes.next(), codes.next(), codes.next()) {
+        (Some(Some(index)), Some(Some(worktree)), None) => {
+            let mut entry = GitStatusFile::new(path, index, worktree);
+            entry.submodule = submodule(record, sub)?;
+            Ok(entry)
+        }
+        _ => Err(format!("invalid status code `{xy}` in `{}`", lossy(record))),
+    }
+}
+
+fn submodule(record: &[u8], sub: &str) -> Result<Option<SubmoduleStatus>, String> {
+    match sub.as_bytes() {
+        b"N..." => Ok(None),
+        [b'S', commit, modified, untracked] => Ok(Some(SubmoduleStatus {
+            commit_changed: *commit == b'C',
+            modified: *modified == b'M',
+            untracked: *untracked == b'U',
+        })),
+        _ => Err(format!(
+            "invalid submodule state `{sub}` in `{}`",
+            lossy(record)
+        )),
+    }
+}
+
+impl Repo {
+    /// Status of the work tree, ignored files included when `include_ignored` is set.
+    pub fn git_status(&self, include_ignored: bool) -> Result<Vec<GitStatusFile>, GitError> {
+        let mut cmd = self.git();
+        cmd.args(["status", "--porcelain=v2", "-z"]);
+        if include_ignored {
+            cmd.arg("--ignored");
+        }
+        let stdout = self.run_stdout_bytes(&mut cmd)?;
+        parse_porcelain_v2(&stdout).map_err(|message| GitError::parse(&cmd, message))
+    }
+
+    /// Files of `file_paths` with unstaged changes, relative to the directory git runs from like
+    /// `file_paths` and [`Repo::get_files_with_word`].
+    pub fn modified_files(&self, file_paths: &[PathBuf]) -> Result<Option<Vec<PathBuf>>, GitError> {
+        let files = self.backend().diff_name_only(self, file_paths)?;
+        if files.is_empty() {
+            return Ok(None);
+        }
+        // git diff prints paths from the top-level directory, the pathspecs are from ours
+        let top_level = self.repo_top_level_dir()?.canonicalize()?;
+        let work_tree = self.work_tree().canonicalize()?;
+        let prefix = work_tree.strip_prefix(&top_level).unwrap_or(Path::new(""));
+        Ok(Some(
+            files.iter().map(|file| relative_to(file, prefix)).collect(),
+        ))
+    }
+
+    pub fn files_are_tracked(&self, file_paths: &[PathBuf]) -> Result<bool, GitError> {
+        self.backend().ls_files(self, file_paths)
+    }
 }
 
-pub fn files_are_tracked(file_paths: &Vec<String>) -> bool {
-    Command::new("git")
-        .args(["ls-files", "--error-unmatch"])
-        .args(file_paths)
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null())
-        .status()
-        .expect("Failed ")
-        .success()
+/// `path` as seen from `dir`, both relative to the same directory, going up with `..` as needed.
+pub(crate) fn relative_to(path: &Path, dir: &Path) -> PathBuf {
+    let mut path = path.components().peekable();
+    let mut dir = dir.components().peekable();
+    while path.peek().is_some() && path.peek() == dir.peek() {
+        path.next();
+        dir.next();
+    }
+    dir.map(|_| Component::ParentDir).chain(path).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn porcelain_v2() {
+        let output = concat!(
+            "# branch.oid 9a4674b\0",
+            "1 .M N... 100644 100644 100644 587be6b 587be6b c.txt\0",
+            "2 R. N... 100644 100644 100644 d68dd40 d68dd40 R087 new name.txt\0a b.txt\0",
+            "u UU N... 100644 100644 100644 100644 df967b9 1f7391f 38f8e88 conf\0",
+            "u DU N... 100644 000000 100644 100644 df967b9 0000000 38f8e88 gone\0",
+            "1 .M SC.U 160000 160000 160000 1f7391f 1f7391f vendor/lib\0",
+            "? new.txt\0",
+            "! ign\0",
+        );
+        let entries = parse_porcelain_v2(output.as_bytes()).unwrap();
+        assert_eq!(entries.len(), 7);
+
+        assert_eq!(entries[0].path, Path::new("c.txt"));
+        assert_eq!(entries[0].index, GitStatus::Unmodified);
+        assert_eq!(entries[0].worktree, GitStatus::Modified);
+        assert!(!entries[0].is_staged());
+
+        assert_eq!(entries[1].path, Path::new("new name.txt"));
+        assert_eq!(entries[1].index, GitStatus::Renamed);
+        assert_eq!(
+            entries[1].source,
+            Some(RenameSource {
+                path: "a b.txt".into(),
+                score: 87
+            })
+        );
+        assert!(entries[1].is_staged());
+
+        assert!(entries[2].is_unmerged());
+        assert_eq!(
+            entries[3].conflict,
+            Some(ConflictStages {
+                base: Some("df967b9".to_string()),
+                ours: None,
+                theirs: Some("38f8e88".to_string()),
+            })
+        );
+
+        assert_eq!(
+            entries[4].submodule,
+            Some(SubmoduleStatus {
+                commit_changed: true,
+                modified: false,
+                untracked: true,
+            })
+        );
+        assert_eq!(entries[5].worktree, GitStatus::Untracked);
+        assert_eq!(entries[6].index, GitStatus::Ignored);
+    }
+
+    #[test]
+    fn porcelain_v2_errors() {
+        assert_eq!(parse_porcelain_v2(b"").unwrap(), vec![]);
+        assert!(parse_porcelain_v2(b"1 .M N... 100644\0").is_err());
+        assert!(parse_porcelain_v2(b"2 R. N... 100644 100644 100644 a b R100 x\0").is_err());
+        assert!(parse_porcelain_v2(b"1 XY N... 1 1 1 a b path\0").is_err());
+        assert!(parse_porcelain_v2(b"z what\0").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn porcelain_v2_raw_paths() {
+        use std::os::unix::ffi::OsStrExt;
+        let output = b"? caf\xe9\n-x.txt\0";
+        let entries = parse_porcelain_v2(output).unwrap();
+        assert_eq!(entries[0].path.as_os_str().as_bytes(), b"caf\xe9\n-x.txt");
+    }
+
+    #[test]
+    fn relative_paths() {
+        let relative = |path: &str, dir: &str| relative_to(Path::new(path), Path::new(dir));
+        assert_eq!(relative("sub/a.txt", "sub"), Path::new("a.txt"));
+        assert_eq!(relative("a.txt", "sub/deeper"), Path::new("../../a.txt"));
+        assert_eq!(relative("sub/x/a.txt", "sub/y"), Path::new("../x/a.txt"));
+        assert_eq!(relative("a.txt", ""), Path::new("a.txt"));
+    }
 }
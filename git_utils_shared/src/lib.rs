@@ -1,143 +1,194 @@
+use backend::LocalBranch;
 use chrono::{DateTime, FixedOffset};
-use std::process::{Command, Stdio};
+use runner::{Runner, System};
+use std::{path::PathBuf, process::Command};
+pub mod backend;
+pub mod cli;
+pub mod complete;
+pub mod diff;
 pub mod egit;
 pub mod embed;
+pub mod error;
 pub mod file;
-// fn run_command(command: &str, args: [&str]) -> () {}
-
-/// Get the merge base between the two provided branches
-pub fn get_merge_base(branch1: &str, branch2: &str) -> Option<String> {
-    let merge_base_vec = Command::new("git")
-        .arg("merge-base")
-        .arg(branch1)
-        .arg(branch2)
-        .output()
-        .expect("Failed to run git merge-base")
-        .stdout;
-
-    let merge_base = String::from_utf8(merge_base_vec).unwrap();
-    let merge_base = merge_base.trim().to_owned();
-
-    if merge_base.trim().is_empty() {
-        return None;
-    } else {
-        return Some(merge_base);
-    }
+pub mod log;
+pub mod man;
+pub mod merge;
+pub mod repo;
+pub mod report;
+pub mod runner;
+pub mod settings;
+pub mod terminal;
+pub mod trace;
+
+pub use error::GitError;
+pub use repo::Repo;
+
+pub(crate) fn into_string(cmd: &Command, raw: Vec<u8>) -> Result<String, GitError> {
+    String::from_utf8(raw).map_err(|err| GitError::NonUtf8 {
+        command: error::command_line(cmd),
+        source: err,
+    })
 }
 
-/// Show commits on the second branch that are not on the first branch
-pub fn show_uncommon_commit_from_other_branch(branch: &str, other_branch: &str) {
-    let _cmd1 = Command::new("git")
-        .arg("--no-pager")
-        .arg("log")
-        .arg("--pretty=format:%Cgreen%h%Creset %Cred(%an)%Creset [%ad] %Cblue%s%Creset ")
-        .arg(branch)
-        .arg(format!("^{}", other_branch))
-        .status()
-        .expect("Failed to retrieve unique commits");
-    println!("\n");
+/// Split NUL delimited output (`-z`), dropping the trailing terminator.
+pub(crate) fn split_nul(stdout: &str) -> Vec<String> {
+    stdout
+        .split_terminator('\0')
+        .map(|path| path.to_owned())
+        .collect()
 }
 
-/// Show the common commit
-pub fn show_common_commit(merge_base: &str) {
-    let _cmd3 = Command::new("git")
-        .arg("--no-pager")
-        .arg("log")
-        .arg("-1")
-        .arg(&merge_base)
-        .status()
-        .expect("Failed to get first common commit");
+/// A path as git prints it, which is any sequence of bytes on Unix.
+pub(crate) fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    return <std::ffi::OsStr as std::os::unix::ffi::OsStrExt>::from_bytes(bytes).into();
+    #[cfg(not(unix))]
+    return String::from_utf8_lossy(bytes).into_owned().into();
 }
 
-pub fn get_files_with_word(search: &str, paths: &Vec<String>) -> Option<Vec<String>> {
-    // assert!(search.contains("\""), "search word can't contain '\"' ");
-    // assert!(search.contains("'"), "search word can't contain "\'" ");
-    let stdout_raw = Command::new("git")
-        .args([
-            "--no-pager",
-            "grep",
-            "--files-with-matches",
-            "--name-only",
-            "-I", // ignore binary files
-            "-z", // null character as delimiter
-            "-E", // extended regex
-            "-e", // pattern
-            // todo replace ' and " in search
-            &search,
-            "--",
-        ])
-        .args(paths)
-        .output()
-        .expect("Failed")
-        .stdout;
+/// Split NUL delimited paths (`-z`), dropping the trailing terminator.
+pub(crate) fn split_nul_paths(stdout: &[u8]) -> Vec<PathBuf> {
+    let stdout = stdout.strip_suffix(b"\0").unwrap_or(stdout);
+    if stdout.is_empty() {
+        return Vec::new();
+    }
+    stdout
+        .split(|&byte| byte == 0)
+        .map(path_from_bytes)
+        .collect()
+}
 
-    let stdout_str = String::from_utf8(stdout_raw).unwrap();
+impl Repo {
+    /// Get the merge base between the two provided branches
+    pub fn get_merge_base(&self, branch1: &str, branch2: &str) -> Result<Option<String>, GitError> {
+        self.backend().merge_base(self, branch1, branch2)
+    }
 
-    if stdout_str.is_empty() {
-        return None;
+    /// Every best common ancestor of all `branches`: several after criss-cross merges, none
+    /// when the histories are unrelated.
+    pub fn get_merge_bases(&self, branches: &[&str]) -> Result<Vec<String>, GitError> {
+        self.backend().merge_bases(self, branches)
     }
 
-    // Because the output of the command is a string with a null character at the end, remove the last element
-    let files = stdout_str[..stdout_str.len() - 1]
-        .split("\0")
-        .map(|path| path.to_owned())
-        .collect::<Vec<String>>();
+    /// Files under `paths` containing `search`, relative to the directory git runs from like
+    /// `paths` and [`Repo::modified_files`].
+    pub fn get_files_with_word(
+        &self,
+        search: &str,
+        paths: &[PathBuf],
+    ) -> Result<Option<Vec<PathBuf>>, GitError> {
+        let files = self.backend().grep(self, search, paths)?;
+        if files.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(files))
+    }
 
-    Some(files)
-}
+    pub fn clone_repo(&self, git_url: &str, path: &str) -> Result<(), GitError> {
+        self.backend().clone_repo(self, git_url, path)
+    }
 
-pub fn clone(git_url: &str, path: &str) -> bool {
-    Command::new("git")
-        .args(["clone", "--quiet", git_url, path])
-        .stdin(Stdio::inherit())
-        .spawn()
-        .unwrap()
-        .wait()
-        .unwrap()
-        .success()
-}
+    pub fn repo_top_level_dir(&self) -> Result<PathBuf, GitError> {
+        self.backend().top_level_dir(self)
+    }
+
+    /// Committer date of HEAD, `None` on a branch without commits yet.
+    pub fn get_last_commit_time(&self) -> Result<Option<DateTime<FixedOffset>>, GitError> {
+        match self.show_commit("HEAD") {
+            Ok(commit) => Ok(Some(commit.committer.date)),
+            Err(_) if self.head_is_unborn()? => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
 
-pub fn repo_top_level_dir() -> Result<std::path::PathBuf, std::io::Error> {
-    let raw_output = Command::new("git")
-        .args(["rev-parse", "--show-toplevel"])
-        .output()?
-        .stdout;
+    /// Whether HEAD is on a branch without commits yet, like right after `git init`.
+    fn head_is_unborn(&self) -> Result<bool, GitError> {
+        let mut cmd = self.git();
+        cmd.args(["rev-parse", "--verify", "--quiet", "HEAD"]);
+        let output = self.run_output(&mut cmd)?;
+        match output.status.code() {
+            Some(0) => Ok(false),
+            // exits with 1 and prints nothing when HEAD names no commit
+            Some(1) if output.stderr.is_empty() => Ok(true),
+            code => Err(GitError::failed(&cmd, code, &output.stderr)),
+        }
+    }
+
+    pub fn get_head(&self) -> Result<String, GitError> {
+        self.rev_parse("HEAD")
+    }
+
+    /// Full hash of the object `rev` names.
+    pub fn rev_parse(&self, rev: &str) -> Result<String, GitError> {
+        self.backend().rev_parse(self, rev)
+    }
+
+    /// Local branches with the upstream they track, sorted by name.
+    pub fn local_branches(&self) -> Result<Vec<LocalBranch>, GitError> {
+        self.backend().local_branches(self)
+    }
 
-    let stdout_str = String::from_utf8(raw_output).unwrap();
-    return Ok(std::path::PathBuf::from(stdout_str.trim()));
+    /// Short names of the branches, remote branches and tags.
+    pub fn ref_names(&self) -> Result<Vec<String>, GitError> {
+        self.backend().ref_names(self)
+    }
+
+    /// Files in the index, relative to the repository directory.
+    pub fn tracked_files(&self) -> Result<Vec<PathBuf>, GitError> {
+        self.backend().tracked_files(self)
+    }
 }
 
 pub fn is_installed(tool: &str) -> bool {
-    Command::new(&tool)
-        .arg("-V")
-        .stdout(std::process::Stdio::null())
-        .status()
+    System
+        .status(
+            Command::new(tool)
+                .arg("-V")
+                .stdout(std::process::Stdio::null()),
+        )
         .is_ok()
 }
 pub fn not_installed(tool: &str) -> bool {
     !is_installed(tool)
 }
 
-pub fn get_last_commit_time() -> Option<DateTime<FixedOffset>> {
-    let stdout_raw = Command::new("git")
-        .args(["log", "-1", "--date=iso-strict", "--format=%cd", "HEAD"])
-        .output()
-        .expect("Failed ")
-        .stdout;
-    let stdout_str = String::from_utf8(stdout_raw).unwrap();
-    if stdout_str.is_empty() {
-        return None;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::Subprocess, log::LOG_FORMAT, runner::MockRunner};
+    use std::sync::Arc;
+
+    #[test]
+    fn last_commit_time_of_unborn_branch() {
+        let log_format = format!("--format={LOG_FORMAT}");
+        let log = [
+            "git",
+            "log",
+            "-z",
+            "--no-color",
+            &log_format,
+            "--max-count=1",
+            "--end-of-options",
+            "HEAD",
+            "--",
+        ];
+        let mock = MockRunner::new()
+            .expect(&log)
+            .exits(128)
+            .stderr("fatal: ambiguous argument 'HEAD': unknown revision")
+            .expect(&["git", "rev-parse", "--verify", "--quiet", "HEAD"])
+            .exits(1)
+            .expect(&log)
+            .exits(128)
+            .stderr("fatal: not a git repository")
+            .expect(&["git", "rev-parse", "--verify", "--quiet", "HEAD"])
+            .exits(128)
+            .stderr("fatal: not a git repository");
+        let repo = Repo::new(".")
+            .with_backend(Arc::new(Subprocess))
+            .with_runner(Arc::new(mock));
+
+        assert_eq!(repo.get_last_commit_time().unwrap(), None);
+        assert_eq!(repo.get_last_commit_time().unwrap_err().exit_code(), 128);
     }
-    let date = chrono::DateTime::parse_from_rfc3339(&stdout_str.trim()).unwrap();
-
-    return Some(date);
-}
-
-pub fn get_head() -> String {
-    let stdout_raw = Command::new("git")
-        .args(["rev-parse", "HEAD"])
-        .output()
-        .expect("Failed")
-        .stdout;
-    String::from_utf8(stdout_raw).unwrap().trim().to_string()
 }
@@ -0,0 +1,305 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+
+use crate::{GitError, Repo};
+
+/// Author or committer of a commit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Signature {
+    pub name: String,
+    pub email: String,
+    pub date: DateTime<FixedOffset>,
+}
+
+/// A commit as returned by [`Repo::log`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Commit {
+    pub hash: String,
+    pub parents: Vec<String>,
+    pub author: Signature,
+    pub committer: Signature,
+    /// First paragraph of the message, joined into one line.
+    pub subject: String,
+    /// Rest of the message after the subject, trailing newlines removed.
+    pub body: String,
+}
+
+impl Commit {
+    /// Abbreviated hash for display.
+    pub fn short_hash(&self) -> &str {
+        &self.hash[..self.hash.len().min(7)]
+    }
+
+    pub fn is_merge(&self) -> bool {
+        self.parents.len() > 1
+    }
+
+    /// Value of the last `<key>: <value>` trailer of the message, e.g. `Change-Id`, the key
+    /// compared case-insensitively.
+    pub fn trailer(&self, key: &str) -> Option<&str> {
+        // trailers are the last paragraph
+        let trailers = self.body.rsplit("\n\n").next()?;
+        trailers.lines().rev().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case(key).then(|| value.trim())
+        })
+    }
+}
+
+/// What [`Repo::log`] should list besides the given revisions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogOptions {
+    /// Revisions whose history is left out, like `^rev` on the command line.
+    pub hidden: Vec<String>,
+    /// Stop after this many commits.
+    pub max_count: Option<usize>,
+    /// Only the commits matching it.
+    pub filter: LogFilter,
+}
+
+impl LogOptions {
+    pub fn hide<S: Into<String>>(mut self, rev: S) -> Self {
+        self.hidden.push(rev.into());
+        self
+    }
+
+    pub fn max_count(mut self, max_count: usize) -> Self {
+        self.max_count = Some(max_count);
+        self
+    }
+
+    pub fn filter(mut self, filter: LogFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+}
+
+/// Commit limiting options of `git log`; a commit is listed when it matches all of them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    /// Regex matched against `Name <email>` of the author, `--author`.
+    pub author: Option<String>,
+    /// Committed after this date, in any format git understands, `--since`.
+    pub since: Option<String>,
+    /// Committed before this date, `--until`.
+    pub until: Option<String>,
+    /// Regex matched against the message, `--grep`.
+    pub grep: Option<String>,
+    /// Leave out commits with more than one parent.
+    pub no_merges: bool,
+    /// Only commits changing these pathspecs, relative to the work tree of the [`Repo`].
+    pub paths: Vec<PathBuf>,
+}
+
+impl LogFilter {
+    /// Whether every commit matches.
+    pub fn is_empty(&self) -> bool {
+        *self == LogFilter::default()
+    }
+
+    /// The options as `git log` and `git rev-list` take them, pathspecs excluded.
+    pub(crate) fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let options = [
+            ("author", &self.author),
+            ("since", &self.since),
+            ("until", &self.until),
+            ("grep", &self.grep),
+        ];
+        for (option, value) in options {
+            if let Some(value) = value {
+                args.push(format!("--{option}={value}"));
+            }
+        }
+        if self.no_merges {
+            args.push("--no-merges".to_string());
+        }
+        args
+    }
+}
+
+/// Pretty format matching [`parse_log`]: ten NUL separated fields per commit, used with `-z`.
+pub const LOG_FORMAT: &str = "%H%x00%P%x00%an%x00%ae%x00%aI%x00%cn%x00%ce%x00%cI%x00%s%x00%b";
+const LOG_FIELDS: usize = 10;
+
+/// Parse the output of `git log -z --format=<LOG_FORMAT>`.
+pub fn parse_log(output: &str) -> Result<Vec<Commit>, String> {
+    let fields: Vec<&str> = output.split_terminator('\0').collect();
+    if !fields.len().is_multiple_of(LOG_FIELDS) {
+        return Err(format!(
+            "expected {LOG_FIELDS} fields per commit, got {} fields",
+            fields.len()
+        ));
+    }
+
+    let date = |value: &str| {
+        DateTime::parse_from_rfc3339(value).map_err(|err| format!("invalid date `{value}`: {err}"))
+    };
+
+    fields
+        .chunks(LOG_FIELDS)
+        .map(|commit| {
+            Ok(Commit {
+                hash: commit[0].trim_start_matches('\n').to_string(),
+                parents: commit[1].split_whitespace().map(String::from).collect(),
+                author: Signature {
+                    name: commit[2].to_string(),
+                    email: commit[3].to_string(),
+                    date: date(commit[4])?,
+                },
+                committer: Signature {
+                    name: commit[5].to_string(),
+                    email: commit[6].to_string(),
+                    date: date(commit[7])?,
+                },
+                subject: commit[8].to_string(),
+                body: commit[9].trim_end_matches('\n').to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Split a range like `a..b`, `^a b` or `a...b` into shown and hidden revisions.
+pub(crate) fn split_range<'a>(range: &[&'a str]) -> (Vec<&'a str>, Vec<&'a str>) {
+    let mut revs = Vec::new();
+    let mut hidden = Vec::new();
+    for rev in range {
+        if let Some(rev) = rev.strip_prefix('^') {
+            hidden.push(rev);
+        } else if let Some((from, to)) = rev.split_once("..") {
+            if !to.starts_with('.') {
+                hidden.push(if from.is_empty() { "HEAD" } else { from });
+                revs.push(if to.is_empty() { "HEAD" } else { to });
+                continue;
+            }
+            revs.push(rev);
+        } else {
+            revs.push(rev);
+        }
+    }
+    (revs, hidden)
+}
+
+impl Repo {
+    /// Commits reachable from `range` (e.g. `["dev", "^master"]` or `["master..dev"]`), newest first.
+    pub fn log(&self, range: &[&str], options: &LogOptions) -> Result<Vec<Commit>, GitError> {
+        let (revs, mut hidden) = split_range(range);
+        hidden.extend(options.hidden.iter().map(String::as_str));
+        self.backend()
+            .log(self, &revs, &hidden, options.max_count, &options.filter)
+    }
+
+    /// Number of commits [`Repo::log`] would list, without reading them.
+    pub fn count(&self, range: &[&str], options: &LogOptions) -> Result<usize, GitError> {
+        let (revs, mut hidden) = split_range(range);
+        hidden.extend(options.hidden.iter().map(String::as_str));
+        self.backend().count(self, &revs, &hidden, &options.filter)
+    }
+
+    /// Patch IDs of the commits [`Repo::log`] lists, keyed by commit hash.
+    ///
+    /// Commits with the same patch ID make the same change, e.g. a commit and its cherry-pick.
+    pub fn patch_ids(
+        &self,
+        range: &[&str],
+        options: &LogOptions,
+    ) -> Result<HashMap<String, String>, GitError> {
+        let (revs, mut hidden) = split_range(range);
+        hidden.extend(options.hidden.iter().map(String::as_str));
+        self.backend().patch_ids(self, &revs, &hidden)
+    }
+
+    /// The single commit `rev` points to.
+    pub fn show_commit(&self, rev: &str) -> Result<Commit, GitError> {
+        let commits = self.log(&[rev], &LogOptions::default().max_count(1))?;
+        commits.into_iter().next().ok_or_else(|| GitError::Backend {
+            operation: "log".to_string(),
+            message: format!("{rev} is not a commit"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let output = concat!(
+            "c3\0c1 c2\0Ann\0ann@example.com\02022-05-01T10:00:00+02:00\0",
+            "Bob\0bob@example.com\02022-05-02T10:00:00Z\0Merge dev\0Body line\n\nmore\n\n\0",
+            "c1\0\0Ann\0ann@example.com\02022-04-01T10:00:00+02:00\0",
+            "Ann\0ann@example.com\02022-04-01T10:00:00+02:00\0Initial\0\0",
+        );
+        let commits = parse_log(output).unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].hash, "c3");
+        assert_eq!(commits[0].parents, vec!["c1", "c2"]);
+        assert!(commits[0].is_merge());
+        assert_eq!(commits[0].author.email, "ann@example.com");
+        assert_eq!(commits[0].committer.name, "Bob");
+        assert_eq!(
+            commits[0].committer.date.to_rfc3339(),
+            "2022-05-02T10:00:00+00:00"
+        );
+        assert_eq!(commits[0].body, "Body line\n\nmore");
+        assert_eq!(commits[1].subject, "Initial");
+        assert!(commits[1].parents.is_empty());
+        assert_eq!(commits[1].body, "");
+
+        assert_eq!(parse_log("").unwrap(), vec![]);
+        assert!(parse_log("c1\0only\0").is_err());
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(split_range(&["a..b"]), (vec!["b"], vec!["a"]));
+        assert_eq!(split_range(&["..b"]), (vec!["b"], vec!["HEAD"]));
+        assert_eq!(split_range(&["b", "^a"]), (vec!["b"], vec!["a"]));
+        assert_eq!(split_range(&["a...b"]), (vec!["a...b"], vec![]));
+    }
+
+    #[test]
+    fn trailers() {
+        let commit = |body: &str| Commit {
+            hash: "c1".to_string(),
+            parents: Vec::new(),
+            author: Signature {
+                name: "Ann".to_string(),
+                email: "ann@example.com".to_string(),
+                date: DateTime::parse_from_rfc3339("2022-05-01T10:00:00+02:00").unwrap(),
+            },
+            committer: Signature {
+                name: "Ann".to_string(),
+                email: "ann@example.com".to_string(),
+                date: DateTime::parse_from_rfc3339("2022-05-01T10:00:00+02:00").unwrap(),
+            },
+            subject: "Fix".to_string(),
+            body: body.to_string(),
+        };
+        let fixed = commit("Details: not a trailer\n\nchange-id: I12ab\nSigned-off-by: Ann");
+        assert_eq!(fixed.trailer("Change-Id"), Some("I12ab"));
+        assert_eq!(fixed.trailer("Details"), None);
+        assert_eq!(commit("").trailer("Change-Id"), None);
+    }
+
+    #[test]
+    fn filter_args() {
+        assert!(LogFilter::default().is_empty());
+        assert!(LogFilter::default().args().is_empty());
+        let filter = LogFilter {
+            author: Some("ann@".to_string()),
+            since: Some("2 weeks ago".to_string()),
+            no_merges: true,
+            paths: vec![PathBuf::from("src")],
+            ..LogFilter::default()
+        };
+        assert!(!filter.is_empty());
+        assert_eq!(
+            filter.args(),
+            ["--author=ann@", "--since=2 weeks ago", "--no-merges"]
+        );
+    }
+}
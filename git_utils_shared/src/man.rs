@@ -0,0 +1,63 @@
+//! Man pages of the tools, rendered by clap_mangen from their clap definition.
+
+use std::io;
+
+use clap::Command;
+
+/// The man pages of `command` as `(file name, roff)`: `<name>.1`, then `<name>-<subcommand>.1`
+/// for each subcommand, the pages the SUBCOMMANDS section of its parent refers to.
+pub fn pages(mut command: Command<'static>) -> io::Result<Vec<(String, Vec<u8>)>> {
+    command.build();
+    let name = command.get_name().to_string();
+    let mut page = Vec::new();
+    clap_mangen::Man::new(command.clone()).render(&mut page)?;
+    let mut pages = vec![(format!("{name}.1"), page)];
+
+    for sub in command.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        let mut sub = sub.clone().name(format!("{name}-{}", sub.get_name()));
+        if let Some(version) = command.get_version() {
+            sub = sub.version(version);
+        }
+        pages.extend(self::pages(sub)?);
+    }
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+
+    #[test]
+    fn page_per_subcommand() {
+        let command = Command::new("git-tool")
+            .version("1.0")
+            .about("Do things")
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .global(true)
+                    .takes_value(true)
+                    .possible_values(["human", "json"]),
+            )
+            .subcommand(
+                Command::new("remove")
+                    .about("Remove a thing")
+                    .arg(Arg::new("path").required(true).help("Thing to remove")),
+            );
+        let pages = pages(command).unwrap();
+        let names: Vec<&str> = pages.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["git-tool.1", "git-tool-remove.1", "git-tool-help.1"]
+        );
+
+        let main = String::from_utf8(pages[0].1.clone()).unwrap();
+        assert!(main.contains(".TH git-tool 1  \"git-tool 1.0\""));
+        assert!(main.contains("git\\-tool\\-remove(1)\nRemove a thing"));
+        let remove = String::from_utf8(pages[1].1.clone()).unwrap();
+        assert!(remove.contains(".TH git-tool-remove 1  \"git-tool-remove 1.0\""));
+        assert!(remove.contains("\\fB\\-\\-format\\fR"));
+        assert!(remove.contains("<\\fIpath\\fR>\nThing to remove"));
+    }
+}
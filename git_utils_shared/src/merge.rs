@@ -0,0 +1,151 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::{path_from_bytes, report::lossy_paths, GitError, Repo};
+
+/// Outcome of merging two commits in memory, as computed by [`Repo::merge_tree`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct MergeTree {
+    /// Tree the merge would record, with conflict markers in the conflicted files.
+    pub tree: String,
+    /// Paths left with more than one stage, in git's order.
+    #[serde(serialize_with = "lossy_paths")]
+    pub conflicted: Vec<PathBuf>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl MergeTree {
+    pub fn is_clean(&self) -> bool {
+        self.conflicted.is_empty() && self.conflicts.is_empty()
+    }
+}
+
+/// One conflict reported by the merge.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Conflict {
+    /// Conflict type as git prints it in the message, e.g. `content`, `add/add` or
+    /// `rename/delete`.
+    pub kind: String,
+    /// Paths involved, e.g. both names of a renamed file.
+    #[serde(serialize_with = "lossy_paths")]
+    pub paths: Vec<PathBuf>,
+    /// What git would print about it, e.g. `CONFLICT (content): Merge conflict in a`.
+    pub message: String,
+}
+
+/// Parse the output of `git merge-tree --write-tree -z`.
+///
+/// That is the tree, then `<mode> <object> <stage>\t<path>` for every stage of the conflicted
+/// files, an empty field, and `<count>`, `<path>...`, `<type>`, `<message>` for every message.
+pub fn parse_merge_tree(output: &[u8]) -> Result<MergeTree, String> {
+    let output = output.strip_suffix(b"\0").unwrap_or(output);
+    let mut fields = output.split(|&byte| byte == 0);
+    let tree = fields
+        .next()
+        .map(|tree| String::from_utf8_lossy(tree).trim().to_string())
+        .filter(|tree| !tree.is_empty())
+        .ok_or("missing tree")?;
+
+    let mut conflicted: Vec<PathBuf> = Vec::new();
+    for entry in fields.by_ref() {
+        if entry.is_empty() {
+            break;
+        }
+        let tab = entry
+            .iter()
+            .position(|&byte| byte == b'\t')
+            .ok_or_else(|| format!("invalid stage entry `{}`", entry.escape_ascii()))?;
+        let path = path_from_bytes(&entry[tab + 1..]);
+        if !conflicted.contains(&path) {
+            conflicted.push(path);
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    while let Some(count) = fields.next() {
+        let count: usize = std::str::from_utf8(count)
+            .ok()
+            .and_then(|count| count.parse().ok())
+            .ok_or_else(|| format!("invalid path count `{}`", count.escape_ascii()))?;
+        let paths: Vec<PathBuf> = fields.by_ref().take(count).map(path_from_bytes).collect();
+        let (Some(kind), Some(message)) = (fields.next(), fields.next()) else {
+            return Err("truncated message".to_string());
+        };
+        // `Auto-merging` and the like are informational
+        let kind = String::from_utf8_lossy(kind);
+        let Some(kind) = kind
+            .strip_prefix("CONFLICT (")
+            .and_then(|kind| kind.strip_suffix(')'))
+        else {
+            continue;
+        };
+        // the type field says `contents` for add/add conflicts too, the message tells them apart
+        let message = String::from_utf8_lossy(message).trim_end().to_string();
+        let kind = message
+            .strip_prefix("CONFLICT (")
+            .and_then(|rest| rest.split_once(')'))
+            .map_or(kind, |(kind, _)| kind);
+        conflicts.push(Conflict {
+            kind: kind.to_string(),
+            paths,
+            message,
+        });
+    }
+
+    Ok(MergeTree {
+        tree,
+        conflicted,
+        conflicts,
+    })
+}
+
+impl Repo {
+    /// Merge `one` and `two` without touching the index or the work tree (`git merge-tree`).
+    pub fn merge_tree(&self, one: &str, two: &str) -> Result<MergeTree, GitError> {
+        self.backend().merge_tree(self, one, two)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_tree() {
+        let output = [
+            "1866bf27",
+            "100644 7898 1\ta",
+            "100644 0cfb 2\ta",
+            "100644 d004 3\ta",
+            "100644 28ce 1\tmv2",
+            "",
+            "1\0a\0Auto-merging\0Auto-merging a\n",
+            "1\0a\0CONFLICT (contents)\0CONFLICT (content): Merge conflict in a\n",
+            "1\0b\0CONFLICT (contents)\0CONFLICT (add/add): Merge conflict in b\n",
+            "2\0mv2\0mv\0CONFLICT (rename/delete)\0CONFLICT (rename/delete): mv renamed\n",
+        ]
+        .map(|field| format!("{field}\0"))
+        .concat();
+        let merge = parse_merge_tree(output.as_bytes()).unwrap();
+        assert_eq!(merge.tree, "1866bf27");
+        assert_eq!(merge.conflicted, [PathBuf::from("a"), PathBuf::from("mv2")]);
+        assert_eq!(merge.conflicts.len(), 3);
+        assert_eq!(merge.conflicts[0].kind, "content");
+        assert_eq!(
+            merge.conflicts[0].message,
+            "CONFLICT (content): Merge conflict in a"
+        );
+        assert_eq!(merge.conflicts[1].kind, "add/add");
+        assert_eq!(merge.conflicts[2].kind, "rename/delete");
+        assert_eq!(
+            merge.conflicts[2].paths,
+            [PathBuf::from("mv2"), PathBuf::from("mv")]
+        );
+        assert!(!merge.is_clean());
+
+        let clean = parse_merge_tree(b"4b825dc6\0").unwrap();
+        assert!(clean.is_clean());
+        assert!(parse_merge_tree(b"").is_err());
+    }
+}
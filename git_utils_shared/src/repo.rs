@@ -0,0 +1,186 @@
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Output},
+    sync::Arc,
+};
+
+use crate::{
+    backend::{default_backend, Backend},
+    into_string,
+    runner::{Runner, System},
+    GitError,
+};
+
+/// A git repository the helpers of this crate run against.
+///
+/// Every command is started from `work_tree` instead of the process working directory, so the
+/// same code can target the parent repository or an embedded `.egit` repository explicitly.
+#[derive(Debug, Clone)]
+pub struct Repo {
+    /// Directory git is run from: the work tree itself or a directory inside it.
+    work_tree: PathBuf,
+    /// Explicit git dir, used for embedded repositories whose git dir is `.egit`.
+    git_dir: Option<PathBuf>,
+    /// Extra environment passed to every command.
+    env: Vec<(OsString, OsString)>,
+    /// Implementation of the git operations.
+    backend: Arc<dyn Backend>,
+    /// Starts the commands, real processes unless a test scripted them.
+    runner: Arc<dyn Runner>,
+}
+
+impl Repo {
+    /// Repository found by git from `path`, which may be any directory inside the work tree.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Repo {
+            work_tree: path.into(),
+            git_dir: None,
+            env: Vec::new(),
+            backend: default_backend(),
+            runner: Arc::new(System),
+        }
+    }
+
+    /// Repository containing the current directory.
+    pub fn current() -> Result<Self, GitError> {
+        Ok(Repo::new(std::env::current_dir()?))
+    }
+
+    /// Embedded repository checked out at `path`, whose git dir is `path/.egit`.
+    pub fn embedded<P: Into<PathBuf>>(path: P) -> Self {
+        let work_tree = path.into();
+        let git_dir = work_tree.join(".egit");
+        Repo {
+            work_tree,
+            git_dir: Some(git_dir),
+            env: Vec::new(),
+            backend: default_backend(),
+            runner: Arc::new(System),
+        }
+    }
+
+    /// Embedded repository at `path`, relative to this repository's directory.
+    pub fn embed<P: AsRef<Path>>(&self, path: P) -> Self {
+        let mut embedded = Repo::embedded(self.work_tree.join(path));
+        embedded.env = self.env.clone();
+        embedded.backend = self.backend.clone();
+        embedded.runner = self.runner.clone();
+        embedded
+    }
+
+    /// Repository found from `path`, with the same environment, backend and runner as this one.
+    pub fn at<P: Into<PathBuf>>(&self, path: P) -> Self {
+        Repo {
+            work_tree: path.into(),
+            git_dir: None,
+            ..self.clone()
+        }
+    }
+
+    /// Add an environment variable to every command run against this repository.
+    pub fn with_env<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Self {
+        self.env
+            .push((key.as_ref().to_os_string(), value.as_ref().to_os_string()));
+        self
+    }
+
+    /// Use `backend` for the git operations on this repository.
+    pub fn with_backend(mut self, backend: Arc<dyn Backend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn backend(&self) -> &dyn Backend {
+        self.backend.as_ref()
+    }
+
+    /// Start commands with `runner` instead of spawning them directly.
+    pub fn with_runner(mut self, runner: Arc<dyn Runner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    pub fn runner(&self) -> &dyn Runner {
+        self.runner.as_ref()
+    }
+
+    pub fn work_tree(&self) -> &Path {
+        &self.work_tree
+    }
+
+    pub fn git_dir(&self) -> Option<&Path> {
+        self.git_dir.as_deref()
+    }
+
+    /// Environment a command needs to target this repository, `GIT_DIR`/`GIT_WORK_TREE` included.
+    pub fn envs(&self) -> Vec<(OsString, OsString)> {
+        let mut envs = Vec::new();
+        if let Some(git_dir) = &self.git_dir {
+            envs.push(("GIT_DIR".into(), git_dir.clone().into_os_string()));
+            envs.push((
+                "GIT_WORK_TREE".into(),
+                self.work_tree.clone().into_os_string(),
+            ));
+        }
+        envs.extend(self.env.iter().cloned());
+        envs
+    }
+
+    /// Build a `program` command that runs from this repository's directory with its environment.
+    pub fn command<S: AsRef<OsStr>>(&self, program: S) -> Command {
+        let mut cmd = Command::new(program);
+        cmd.current_dir(&self.work_tree).envs(self.envs());
+        cmd
+    }
+
+    /// Build a `git` command targeting this repository.
+    pub fn git(&self) -> Command {
+        self.command("git")
+    }
+
+    /// Run a command, capturing its output, without looking at its exit status.
+    pub(crate) fn run_output(&self, cmd: &mut Command) -> Result<Output, GitError> {
+        self.runner
+            .output(cmd)
+            .map_err(|err| GitError::spawn(cmd, err))
+    }
+
+    /// Like [`Repo::run_output`], writing `input` to the command's stdin.
+    pub(crate) fn run_output_with_input(
+        &self,
+        cmd: &mut Command,
+        input: &[u8],
+    ) -> Result<Output, GitError> {
+        self.runner
+            .output_with_input(cmd, input)
+            .map_err(|err| GitError::spawn(cmd, err))
+    }
+
+    /// Run a command with inherited stdio and fail if it exits unsuccessfully.
+    pub fn run_status(&self, cmd: &mut Command) -> Result<ExitStatus, GitError> {
+        let status = self
+            .runner
+            .status(cmd)
+            .map_err(|err| GitError::spawn(cmd, err))?;
+        if !status.success() {
+            return Err(GitError::failed(cmd, status.code(), &[]));
+        }
+        Ok(status)
+    }
+
+    /// Run a command and return its stdout, failing if it exits unsuccessfully.
+    pub(crate) fn run_stdout(&self, cmd: &mut Command) -> Result<String, GitError> {
+        let stdout = self.run_stdout_bytes(cmd)?;
+        into_string(cmd, stdout)
+    }
+
+    /// Like [`Repo::run_stdout`] for output holding paths or text that need not be UTF-8.
+    pub(crate) fn run_stdout_bytes(&self, cmd: &mut Command) -> Result<Vec<u8>, GitError> {
+        let output = self.run_output(cmd)?;
+        if !output.status.success() {
+            return Err(GitError::failed(cmd, output.status.code(), &output.stderr));
+        }
+        Ok(output.stdout)
+    }
+}
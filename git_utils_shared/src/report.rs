@@ -0,0 +1,164 @@
+use std::{
+    ffi::OsStr,
+    fmt,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Serialize, Serializer};
+
+use crate::{terminal, GitError};
+
+/// How a tool prints its results, chosen with `--format`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Colored text and tables for people.
+    #[default]
+    Human,
+    /// One JSON document on stdout.
+    Json,
+    /// Stable tab-separated lines, one record per line.
+    Porcelain,
+}
+
+impl Format {
+    /// Values accepted by `--format`.
+    pub const NAMES: [&'static str; 3] = ["human", "json", "porcelain"];
+
+    pub fn is_human(self) -> bool {
+        self == Format::Human
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "human" => Ok(Format::Human),
+            "json" => Ok(Format::Json),
+            "porcelain" => Ok(Format::Porcelain),
+            _ => Err(format!(
+                "unknown format `{value}`, expected one of {}",
+                Format::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Human => "human",
+            Format::Json => "json",
+            Format::Porcelain => "porcelain",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Result of a tool that can be printed in every [`Format`]; JSON comes from `Serialize`.
+pub trait Report: Serialize {
+    fn human(&self, out: &mut dyn Write) -> io::Result<()>;
+
+    /// Write records with [`porcelain_line`] so that scripts can split them reliably.
+    fn porcelain(&self, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Print `report` on stdout in `format`, through the pager when [`terminal::init`] set one up.
+pub fn print<R: Report>(format: Format, report: &R) -> Result<(), GitError> {
+    let mut out = Vec::new();
+    write(&mut out, format, report)?;
+    terminal::page(&out)?;
+    Ok(())
+}
+
+/// Write `report` to `out` in `format`, what [`print`] pages.
+pub fn write<R: Report>(out: &mut dyn Write, format: Format, report: &R) -> io::Result<()> {
+    match format {
+        Format::Human => report.human(out),
+        Format::Porcelain => report.porcelain(out),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, report).map_err(io::Error::from)?;
+            writeln!(out)
+        }
+    }
+}
+
+/// Write one porcelain record: `fields` separated by tabs.
+///
+/// Fields containing a tab, a newline, a backslash, a double quote or bytes that are not UTF-8
+/// are C-quoted the way git quotes paths, so every record stays on one line and paths survive.
+pub fn porcelain_line<S: AsRef<OsStr>>(out: &mut dyn Write, fields: &[S]) -> io::Result<()> {
+    let fields: Vec<String> = fields.iter().map(|field| quote(field.as_ref())).collect();
+    writeln!(out, "{}", fields.join("\t"))
+}
+
+fn quote(field: &OsStr) -> String {
+    let bytes = field.as_encoded_bytes();
+    if let Some(field) = field.to_str() {
+        if !field.contains(['\t', '\n', '\r', '\\', '"']) {
+            return field.to_string();
+        }
+    }
+    let mut quoted = String::from("\"");
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\t' => quoted.push_str("\\t"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\\' => quoted.push_str("\\\\"),
+                '"' => quoted.push_str("\\\""),
+                c => quoted.push(c),
+            }
+        }
+        for byte in chunk.invalid() {
+            quoted.push_str(&format!("\\{byte:03o}"));
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Serialize a path as a string, with bytes that are not UTF-8 replaced.
+///
+/// For `#[serde(serialize_with)]`; JSON has no way to carry such bytes, porcelain output keeps
+/// them.
+pub fn lossy_path<P: AsRef<Path>, S: Serializer>(
+    path: P,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&path.as_ref().to_string_lossy())
+}
+
+/// Like [`lossy_path`] for a list of paths.
+pub fn lossy_paths<S: Serializer>(paths: &[PathBuf], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(paths.iter().map(|path| path.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn porcelain_quoting() {
+        let mut out = Vec::new();
+        porcelain_line(&mut out, &["replaced", "plain.txt"]).unwrap();
+        porcelain_line(&mut out, &["replaced", "tab\there", "new\nline \"q\""]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "replaced\tplain.txt\nreplaced\t\"tab\\there\"\t\"new\\nline \\\"q\\\"\"\n"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let mut out = Vec::new();
+            porcelain_line(&mut out, &[OsStr::from_bytes(b"caf\xe9 \xc3\xa9.txt")]).unwrap();
+            assert_eq!(out, "\"caf\\351 \u{e9}.txt\"\n".as_bytes());
+        }
+        assert_eq!("json".parse::<Format>(), Ok(Format::Json));
+        assert!("yaml".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    collections::VecDeque,
+    ffi::OsStr,
+    fmt,
+    io::{self, Write},
+    process::{Command, ExitStatus, Output, Stdio},
+    sync::Mutex,
+    thread,
+    time::Instant,
+};
+
+use crate::trace;
+
+/// Starts the commands built by [`Repo`](crate::Repo).
+///
+/// [`System`] spawns real processes. Tests swap in a [`MockRunner`] with
+/// [`Repo::with_runner`](crate::Repo::with_runner) to script what each command returns.
+pub trait Runner: fmt::Debug + Send + Sync {
+    /// Run the command to completion, capturing stdout and stderr unless they were redirected.
+    fn output(&self, cmd: &mut Command) -> io::Result<Output>;
+
+    /// Run the command with the stdio it was configured with.
+    fn status(&self, cmd: &mut Command) -> io::Result<ExitStatus>;
+
+    /// Like [`Runner::output`], writing `input` to the command's stdin.
+    fn output_with_input(&self, cmd: &mut Command, input: &[u8]) -> io::Result<Output>;
+}
+
+/// Runner spawning real processes, logging each one when [`trace`] is enabled.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct System;
+
+impl Runner for System {
+    fn output(&self, cmd: &mut Command) -> io::Result<Output> {
+        let start = Instant::now();
+        let output = cmd.output();
+        let result = output.as_ref().map(|output| output.status.code());
+        trace::command(cmd, result, start.elapsed());
+        output
+    }
+
+    fn status(&self, cmd: &mut Command) -> io::Result<ExitStatus> {
+        let start = Instant::now();
+        let status = cmd.status();
+        let result = status.as_ref().map(|status| status.code());
+        trace::command(cmd, result, start.elapsed());
+        status
+    }
+
+    fn output_with_input(&self, cmd: &mut Command, input: &[u8]) -> io::Result<Output> {
+        let start = Instant::now();
+        let output = feed(cmd, input);
+        let result = output.as_ref().map(|output| output.status.code());
+        trace::command(cmd, result, start.elapsed());
+        output
+    }
+}
+
+/// Write `input` to the command's stdin while reading its output, so that neither pipe fills up.
+fn feed(cmd: &mut Command, input: &[u8]) -> io::Result<Output> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    thread::scope(|scope| {
+        let writer = scope.spawn(move || stdin.write_all(input));
+        let output = child.wait_with_output()?;
+        match writer.join().expect("writing stdin panicked") {
+            // the command may exit without reading everything
+            Err(err) if err.kind() != io::ErrorKind::BrokenPipe => Err(err),
+            _ => Ok(output),
+        }
+    })
+}
+
+/// A command as it was run: program, arguments and explicitly set environment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Invocation {
+    pub argv: Vec<String>,
+    /// Variables set (`Some`) or removed (`None`) on the command, in the order they were given.
+    pub env: Vec<(String, Option<String>)>,
+}
+
+impl Invocation {
+    pub fn of(cmd: &Command) -> Self {
+        let lossy = |value: &OsStr| value.to_string_lossy().into_owned();
+        Invocation {
+            argv: std::iter::once(cmd.get_program())
+                .chain(cmd.get_args())
+                .map(lossy)
+                .collect(),
+            env: cmd
+                .get_envs()
+                .map(|(key, value)| (lossy(key), value.map(lossy)))
+                .collect(),
+        }
+    }
+
+    /// Value `key` is set to, `None` when it is not set or was removed.
+    pub fn env(&self, key: &str) -> Option<&str> {
+        self.env
+            .iter()
+            .rev()
+            .find(|(name, _)| name == key)
+            .and_then(|(_, value)| value.as_deref())
+    }
+}
+
+/// One scripted command of a [`MockRunner`] and what it returns.
+#[derive(Clone, Debug)]
+pub struct Expectation {
+    pub argv: Vec<String>,
+    /// Variables the command must set (`Some`) or remove (`None`).
+    pub env: Vec<(String, Option<String>)>,
+    /// What the command must be given on stdin, `None` when it is not fed anything.
+    pub input: Option<Vec<u8>>,
+    pub code: i32,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// Runner replaying a script of expected commands, for tests.
+///
+/// Every command has to match the next expectation exactly, otherwise the runner panics. It
+/// also panics when dropped with expectations left over, so a test fails when a command it
+/// scripted was never run.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use git_utils_shared::{backend::Subprocess, runner::MockRunner, Repo};
+/// let mock = MockRunner::new()
+///     .expect(&["git", "merge-base", "--end-of-options", "master", "dev"])
+///     .exits(1);
+/// let repo = Repo::new(".")
+///     .with_backend(Arc::new(Subprocess))
+///     .with_runner(Arc::new(mock));
+/// assert_eq!(repo.get_merge_base("master", "dev").unwrap(), None);
+/// ```
+#[derive(Debug, Default)]
+pub struct MockRunner {
+    expected: Mutex<VecDeque<Expectation>>,
+}
+
+impl MockRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Script the next command, which succeeds with empty output unless configured otherwise.
+    pub fn expect(self, argv: &[&str]) -> Self {
+        self.push(Expectation {
+            argv: argv.iter().map(|arg| arg.to_string()).collect(),
+            env: Vec::new(),
+            input: None,
+            code: 0,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        })
+    }
+
+    /// Script a command recorded earlier, e.g. with a [`Recorder`].
+    pub fn push(self, expectation: Expectation) -> Self {
+        self.expected.lock().unwrap().push_back(expectation);
+        self
+    }
+
+    /// Require the last scripted command to set `key` to `value`.
+    pub fn env(self, key: &str, value: &str) -> Self {
+        self.last(|expected| expected.env.push((key.into(), Some(value.into()))))
+    }
+
+    /// Require the last scripted command to remove `key` from its environment.
+    pub fn env_removed(self, key: &str) -> Self {
+        self.last(|expected| expected.env.push((key.into(), None)))
+    }
+
+    /// Require the last scripted command to be given `input` on stdin.
+    pub fn input(self, input: &str) -> Self {
+        self.last(|expected| expected.input = Some(input.as_bytes().to_vec()))
+    }
+
+    /// Output of the last scripted command.
+    pub fn stdout(self, stdout: &str) -> Self {
+        self.last(|expected| expected.stdout = stdout.as_bytes().to_vec())
+    }
+
+    pub fn stderr(self, stderr: &str) -> Self {
+        self.last(|expected| expected.stderr = stderr.as_bytes().to_vec())
+    }
+
+    /// Exit code of the last scripted command.
+    pub fn exits(self, code: i32) -> Self {
+        self.last(|expected| expected.code = code)
+    }
+
+    /// Whether every scripted command has been run.
+    pub fn is_done(&self) -> bool {
+        self.expected.lock().unwrap().is_empty()
+    }
+
+    fn last(self, update: impl FnOnce(&mut Expectation)) -> Self {
+        update(
+            self.expected
+                .lock()
+                .unwrap()
+                .back_mut()
+                .expect("call `expect` before configuring the command"),
+        );
+        self
+    }
+
+    fn next(&self, cmd: &Command, input: Option<&[u8]>) -> Expectation {
+        let invocation = Invocation::of(cmd);
+        let expected = self
+            .expected
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| panic!("unexpected command {:?}", invocation.argv));
+        assert_eq!(
+            invocation.argv, expected.argv,
+            "command does not match the script"
+        );
+        assert_eq!(
+            input.map(String::from_utf8_lossy),
+            expected.input.as_deref().map(String::from_utf8_lossy),
+            "stdin of {:?}",
+            invocation.argv
+        );
+        for (key, value) in &expected.env {
+            assert_eq!(
+                invocation.env(key),
+                value.as_deref(),
+                "environment variable {key} of {:?}",
+                invocation.argv
+            );
+        }
+        expected
+    }
+}
+
+impl Runner for MockRunner {
+    fn output(&self, cmd: &mut Command) -> io::Result<Output> {
+        let expected = self.next(cmd, None);
+        Ok(Output {
+            status: exit_status(expected.code),
+            stdout: expected.stdout,
+            stderr: expected.stderr,
+        })
+    }
+
+    fn status(&self, cmd: &mut Command) -> io::Result<ExitStatus> {
+        Ok(exit_status(self.next(cmd, None).code))
+    }
+
+    fn output_with_input(&self, cmd: &mut Command, input: &[u8]) -> io::Result<Output> {
+        let expected = self.next(cmd, Some(input));
+        Ok(Output {
+            status: exit_status(expected.code),
+            stdout: expected.stdout,
+            stderr: expected.stderr,
+        })
+    }
+}
+
+impl Drop for MockRunner {
+    fn drop(&mut self) {
+        let expected = self.expected.get_mut().unwrap();
+        if !expected.is_empty() && !std::thread::panicking() {
+            let left: Vec<_> = expected.iter().map(|expected| &expected.argv).collect();
+            panic!("scripted commands were never run: {left:?}");
+        }
+    }
+}
+
+/// Runner passing commands on to another runner and keeping what they returned.
+///
+/// The recorded expectations can be replayed with [`MockRunner::push`] to turn a run against a
+/// real repository into a script.
+#[derive(Debug)]
+pub struct Recorder<R> {
+    inner: R,
+    recorded: Mutex<Vec<Expectation>>,
+}
+
+impl<R: Runner> Recorder<R> {
+    pub fn new(inner: R) -> Self {
+        Recorder {
+            inner,
+            recorded: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Take the commands recorded so far.
+    pub fn take(&self) -> Vec<Expectation> {
+        std::mem::take(&mut *self.recorded.lock().unwrap())
+    }
+
+    fn record(
+        &self,
+        cmd: &Command,
+        input: Option<&[u8]>,
+        status: ExitStatus,
+        output: (&[u8], &[u8]),
+    ) {
+        let (stdout, stderr) = output;
+        let invocation = Invocation::of(cmd);
+        self.recorded.lock().unwrap().push(Expectation {
+            argv: invocation.argv,
+            env: invocation.env,
+            input: input.map(<[u8]>::to_vec),
+            code: status.code().unwrap_or(-1),
+            stdout: stdout.to_vec(),
+            stderr: stderr.to_vec(),
+        });
+    }
+}
+
+impl<R: Runner> Runner for Recorder<R> {
+    fn output(&self, cmd: &mut Command) -> io::Result<Output> {
+        let output = self.inner.output(cmd)?;
+        self.record(cmd, None, output.status, (&output.stdout, &output.stderr));
+        Ok(output)
+    }
+
+    fn status(&self, cmd: &mut Command) -> io::Result<ExitStatus> {
+        let status = self.inner.status(cmd)?;
+        self.record(cmd, None, status, (&[], &[]));
+        Ok(status)
+    }
+
+    fn output_with_input(&self, cmd: &mut Command, input: &[u8]) -> io::Result<Output> {
+        let output = self.inner.output_with_input(cmd, input)?;
+        let streams = (output.stdout.as_slice(), output.stderr.as_slice());
+        self.record(cmd, Some(input), output.status, streams);
+        Ok(output)
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
@@ -0,0 +1,356 @@
+//! Per-repository defaults of the tools, read from `excess.*` git config keys.
+//!
+//! Values are looked up from the lowest to the highest precedence:
+//!
+//! 1. the built-in defaults in [`KEYS`],
+//! 2. `[excess "embed"]` in the committed `.gitembed`, shared by everyone working on the project,
+//! 3. the system, global, local and worktree git config files,
+//! 4. `git -c excess.<key>=<value>` on the command line,
+//! 5. the tool's own command line flags, see [`Settings::value`].
+
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+use serde::Serialize;
+
+use crate::{embed::EMBED_FILE, GitError, Repo};
+
+/// A setting the tools understand.
+#[derive(Clone, Copy, Debug)]
+pub struct Key {
+    pub name: &'static str,
+    pub default: &'static str,
+    pub help: &'static str,
+}
+
+/// Every setting read by the tools, with its built-in default.
+pub const KEYS: &[Key] = &[
+    Key {
+        name: "excess.sdif.format",
+        default: "human",
+        help: "Output format of git-sdif",
+    },
+    Key {
+        name: "excess.sdif.dateFormat",
+        default: "%a %b %-d %H:%M:%S %Y %z",
+        help: "strftime format of the commit dates git-sdif shows",
+    },
+    Key {
+        name: "excess.sdif.defaultBranch",
+        default: "",
+        help: "Branch git sdif --all compares every branch to, main or master when empty",
+    },
+    Key {
+        name: "excess.sdif.staleDays",
+        default: "90",
+        help: "Days without commits after which git sdif --all calls a branch stale",
+    },
+    Key {
+        name: "excess.srep.format",
+        default: "human",
+        help: "Output format of git-srep",
+    },
+    Key {
+        name: "excess.srep.force",
+        default: "false",
+        help: "Replace in files with unstaged changes, like --force",
+    },
+    Key {
+        name: "excess.srep.dryRun",
+        default: "false",
+        help: "Only show what would be replaced, like --dry-run",
+    },
+    Key {
+        name: "excess.embed.format",
+        default: "human",
+        help: "Output format of git-embed",
+    },
+    Key {
+        name: "excess.embed.tagFormat",
+        default: "{project}.{date}.{head}",
+        help: "Name of the tags created by git embed tag",
+    },
+    Key {
+        name: "excess.embed.push",
+        default: "true",
+        help: "Push the tags created by git embed tag",
+    },
+];
+
+/// Where a value was set, from the lowest to the highest precedence.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    /// Built into the tools.
+    Default,
+    /// `[excess "embed"]` in `.gitembed`.
+    Gitembed,
+    System,
+    Global,
+    Local,
+    Worktree,
+    /// `git -c` or `GIT_CONFIG_PARAMETERS`.
+    Command,
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    /// Scope names printed by `git config --show-scope`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "system" => Ok(Scope::System),
+            "global" => Ok(Scope::Global),
+            "local" => Ok(Scope::Local),
+            "worktree" => Ok(Scope::Worktree),
+            "command" => Ok(Scope::Command),
+            _ => Err(format!("unknown config scope `{value}`")),
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Scope::Default => "default",
+            Scope::Gitembed => "gitembed",
+            Scope::System => "system",
+            Scope::Global => "global",
+            Scope::Local => "local",
+            Scope::Worktree => "worktree",
+            Scope::Command => "command",
+        };
+        f.write_str(name)
+    }
+}
+
+/// One value of a setting and where it was set.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Setting {
+    /// Name as git lists it: section and key lowercased.
+    pub key: String,
+    pub value: String,
+    pub scope: Scope,
+    /// `file:<path>` or `command line:` as printed by `git config --show-origin`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+}
+
+impl Setting {
+    /// Scope and origin, e.g. `global file:/home/me/.gitconfig`.
+    pub fn source(&self) -> String {
+        match &self.origin {
+            Some(origin) => format!("{} {origin}", self.scope),
+            None => self.scope.to_string(),
+        }
+    }
+
+    fn invalid(&self, message: impl fmt::Display) -> GitError {
+        GitError::Setting {
+            key: self.key.clone(),
+            value: self.value.clone(),
+            origin: self.source(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// All values of the `excess.*` settings, lowest precedence first.
+#[derive(Clone, Debug, Default)]
+pub struct Settings {
+    values: Vec<Setting>,
+}
+
+impl Settings {
+    /// Settings holding only the built-in defaults.
+    pub fn defaults() -> Self {
+        Settings {
+            values: KEYS
+                .iter()
+                .map(|key| Setting {
+                    key: key.name.to_ascii_lowercase(),
+                    value: key.default.to_string(),
+                    scope: Scope::Default,
+                    origin: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Add values that take precedence over the ones already read.
+    pub fn extend(&mut self, values: impl IntoIterator<Item = Setting>) {
+        self.values.extend(values);
+    }
+
+    /// Every value read, overridden ones included, lowest precedence first.
+    pub fn all(&self) -> &[Setting] {
+        &self.values
+    }
+
+    /// The value of `key` that wins.
+    pub fn get(&self, key: &str) -> Option<&Setting> {
+        self.values
+            .iter()
+            .rev()
+            .find(|setting| setting.key.eq_ignore_ascii_case(key))
+    }
+
+    /// The winning value of every key: the known ones in [`KEYS`] order, then the others by name.
+    pub fn effective(&self) -> Vec<&Setting> {
+        let mut unknown: Vec<&Setting> = self
+            .values
+            .iter()
+            .filter(|setting| {
+                !KEYS
+                    .iter()
+                    .any(|key| key.name.eq_ignore_ascii_case(&setting.key))
+            })
+            .map(|setting| &setting.key)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|key| self.get(key))
+            .collect();
+        let mut effective: Vec<&Setting> =
+            KEYS.iter().filter_map(|key| self.get(key.name)).collect();
+        effective.append(&mut unknown);
+        effective
+    }
+
+    /// `flag` when the command line gave one, otherwise the setting `key` parsed as `T`.
+    ///
+    /// Panics when `key` is not one of [`KEYS`].
+    pub fn value<T>(&self, key: &str, flag: Option<T>) -> Result<T, GitError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        if let Some(flag) = flag {
+            return Ok(flag);
+        }
+        let setting = self.known(key);
+        setting.value.parse().map_err(|err| setting.invalid(err))
+    }
+
+    /// Like [`Settings::value`] for booleans, which git spells in several ways.
+    pub fn bool(&self, key: &str, flag: Option<bool>) -> Result<bool, GitError> {
+        if let Some(flag) = flag {
+            return Ok(flag);
+        }
+        let setting = self.known(key);
+        parse_bool(&setting.value).ok_or_else(|| setting.invalid("expected a boolean"))
+    }
+
+    fn known(&self, key: &str) -> &Setting {
+        assert!(
+            KEYS.iter().any(|known| known.name == key),
+            "{key} is not a known setting"
+        );
+        self.get(key).expect("defaults are always present")
+    }
+}
+
+/// `Some(true)` for `--flag`, `Some(false)` for `--no-flag`, `None` when neither was given.
+pub fn flag(yes: bool, no: bool) -> Option<bool> {
+    match (yes, no) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// A boolean the way `git config --type=bool` reads it.
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" | "" => Some(false),
+        _ => None,
+    }
+}
+
+impl Repo {
+    /// The `excess.*` settings that apply to this repository.
+    ///
+    /// Outside of a repository only the defaults and the system and global config apply.
+    pub fn settings(&self) -> Result<Settings, GitError> {
+        let mut settings = Settings::defaults();
+        if let Ok(top_level) = self.repo_top_level_dir() {
+            let config = self.at(top_level).embed_config()?;
+            settings.extend(
+                config
+                    .entries()
+                    .into_iter()
+                    .filter(|(key, _)| key.starts_with("excess.embed."))
+                    .map(|(key, value)| Setting {
+                        key,
+                        value: value.to_string(),
+                        scope: Scope::Gitembed,
+                        origin: Some(format!("file:{EMBED_FILE}")),
+                    }),
+            );
+        }
+        settings.extend(self.backend().config_scoped(self, "excess.")?);
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::EmbedConfig;
+
+    fn setting(key: &str, value: &str, scope: Scope) -> Setting {
+        Setting {
+            key: key.to_string(),
+            value: value.to_string(),
+            scope,
+            origin: None,
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        let mut settings = Settings::defaults();
+        settings.extend([
+            setting("excess.srep.force", "yes", Scope::Global),
+            setting("excess.srep.dryrun", "maybe", Scope::Local),
+            setting("excess.embed.push", "off", Scope::Gitembed),
+            setting("excess.embed.push", "on", Scope::Command),
+            setting("excess.other.key", "x", Scope::Local),
+        ]);
+
+        assert!(settings.bool("excess.srep.force", None).unwrap());
+        assert!(!settings.bool("excess.srep.force", Some(false)).unwrap());
+        assert!(settings.bool("excess.embed.push", None).unwrap());
+        let err = settings.bool("excess.srep.dryRun", None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "bad value `maybe` for excess.srep.dryrun (local): expected a boolean"
+        );
+        assert_eq!(
+            settings
+                .value::<String>("excess.embed.tagFormat", None)
+                .unwrap(),
+            "{project}.{date}.{head}"
+        );
+
+        let effective = settings.effective();
+        assert_eq!(effective.len(), KEYS.len() + 1);
+        assert_eq!(effective[0].scope, Scope::Default);
+        assert_eq!(effective[KEYS.len()].key, "excess.other.key");
+        assert_eq!(flag(false, true), Some(false));
+    }
+
+    #[test]
+    fn gitembed_entries() {
+        let config = EmbedConfig::parse(
+            "[excess \"embed\"]\n\tTagFormat = {head}\n[embed \"lib\"]\n\turl = x\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.entries(),
+            vec![
+                ("excess.embed.tagformat".to_string(), "{head}"),
+                ("embed.lib.url".to_string(), "x"),
+            ]
+        );
+    }
+}
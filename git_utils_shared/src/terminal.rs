@@ -0,0 +1,162 @@
+//! How the tools' output looks on a terminal: colors and the pager, decided the way git does.
+
+use std::{
+    env, fmt,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use crate::{GitError, Repo};
+
+/// When to color the output, chosen with `--color`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Only when stdout is a terminal.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Values accepted by `--color`.
+    pub const NAMES: [&'static str; 3] = ["auto", "always", "never"];
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!(
+                "unknown color choice `{value}`, expected one of {}",
+                ColorChoice::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ColorChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ColorChoice::Auto => "auto",
+            ColorChoice::Always => "always",
+            ColorChoice::Never => "never",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Pager command the reports are shown with, `None` when they go straight to stdout.
+static PAGER: OnceLock<Option<String>> = OnceLock::new();
+
+/// Set up colors and the pager from the command line flags and the repository config.
+///
+/// Colors follow `color`, then `NO_COLOR`, then git's `color.ui`, and are otherwise only used
+/// when stdout is a terminal. Reports printed with [`report::print`](crate::report::print) go
+/// through the pager when stdout is a terminal, unless `no_pager` is set.
+pub fn init(color: Option<ColorChoice>, no_pager: bool, repo: &Repo) -> Result<(), GitError> {
+    let tty = io::stdout().is_terminal() && env::var_os("TERM").is_none_or(|term| term != "dumb");
+    let color = match color {
+        Some(color) => color,
+        None if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) => {
+            ColorChoice::Never
+        }
+        None => match repo.backend().config_get(repo, "color.ui")? {
+            Some(value) => color_ui(&value),
+            None => ColorChoice::Auto,
+        },
+    };
+    colored::control::set_override(match color {
+        ColorChoice::Auto => tty,
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    });
+
+    let pager = match tty && !no_pager {
+        true => pager(repo)?,
+        false => None,
+    };
+    let _ = PAGER.set(pager);
+    Ok(())
+}
+
+/// `color.ui` as git reads it, where `true` means auto.
+fn color_ui(value: &str) -> ColorChoice {
+    match value.to_ascii_lowercase().as_str() {
+        "always" => ColorChoice::Always,
+        "never" | "false" | "no" | "off" | "0" => ColorChoice::Never,
+        _ => ColorChoice::Auto,
+    }
+}
+
+/// `GIT_PAGER`, `core.pager`, `PAGER` or less, `None` when it is empty or `cat`.
+fn pager(repo: &Repo) -> Result<Option<String>, GitError> {
+    let pager = match env::var("GIT_PAGER") {
+        Ok(pager) => pager,
+        Err(_) => match repo.backend().config_get(repo, "core.pager")? {
+            Some(pager) => pager,
+            None => env::var("PAGER").unwrap_or_else(|_| "less".to_string()),
+        },
+    };
+    Ok(Some(pager).filter(|pager| !pager.trim().is_empty() && pager.trim() != "cat"))
+}
+
+/// Show `output` in the pager set up by [`init`], or write it to stdout.
+pub(crate) fn page(output: &[u8]) -> io::Result<()> {
+    if let Some(Some(pager)) = PAGER.get() {
+        if let Ok(mut child) = pager_command(pager).stdin(Stdio::piped()).spawn() {
+            let mut stdin = child.stdin.take().expect("stdin is piped");
+            // quitting the pager before the end closes the pipe, which is fine
+            match stdin.write_all(output) {
+                Err(err) if err.kind() != io::ErrorKind::BrokenPipe => return Err(err),
+                _ => {}
+            }
+            drop(stdin);
+            child.wait()?;
+            return Ok(());
+        }
+    }
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(output)?;
+    stdout.flush()
+}
+
+/// The pager run by the shell like git does, with git's defaults for less and lv.
+fn pager_command(pager: &str) -> Command {
+    let mut cmd = match cfg!(windows) {
+        true => Command::new(pager),
+        false => {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", pager]);
+            cmd
+        }
+    };
+    if env::var_os("LESS").is_none() {
+        cmd.env("LESS", "FRX");
+    }
+    if env::var_os("LV").is_none() {
+        cmd.env("LV", "-c");
+    }
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_ui_values() {
+        assert_eq!(color_ui("always"), ColorChoice::Always);
+        assert_eq!(color_ui("true"), ColorChoice::Auto);
+        assert_eq!(color_ui("auto"), ColorChoice::Auto);
+        assert_eq!(color_ui("False"), ColorChoice::Never);
+        assert_eq!("never".parse(), Ok(ColorChoice::Never));
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    env,
+    ffi::OsStr,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use crate::{error::command_line, GitError};
+
+/// Set to anything but `0` or an empty string to log commands on stderr, like `--verbose`.
+pub const VERBOSE_ENV: &str = "GIT_EXCESS_VERBOSE";
+/// Path of a file commands are appended to, like `--trace-file`.
+pub const TRACE_FILE_ENV: &str = "GIT_EXCESS_TRACE_FILE";
+
+struct Trace {
+    /// Name of the running tool and its pid, prefixed to every line.
+    tool: String,
+    verbose: bool,
+    file: Option<Mutex<File>>,
+}
+
+static TRACE: OnceLock<Trace> = OnceLock::new();
+
+/// Start logging every spawned command to stderr (`verbose`) and/or to `file`.
+///
+/// The environment variables fill in what the flags leave unset, and are exported again so
+/// that tools started by this one (egit from git-embed) log to the same places.
+pub fn init(verbose: bool, file: Option<&Path>) -> Result<(), GitError> {
+    let verbose = verbose || env::var_os(VERBOSE_ENV).is_some_and(|value| enabled(&value));
+    let file = file
+        .map(Path::to_path_buf)
+        .or_else(|| env::var_os(TRACE_FILE_ENV).map(PathBuf::from))
+        .filter(|file| !file.as_os_str().is_empty());
+
+    if verbose {
+        env::set_var(VERBOSE_ENV, "1");
+    }
+    let file = match file {
+        Some(path) => {
+            let opened = OpenOptions::new().create(true).append(true).open(&path)?;
+            env::set_var(TRACE_FILE_ENV, path.canonicalize()?);
+            Some(Mutex::new(opened))
+        }
+        None => None,
+    };
+    if !verbose && file.is_none() {
+        return Ok(());
+    }
+
+    let tool = env::args_os()
+        .next()
+        .as_deref()
+        .map(Path::new)
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let _ = TRACE.set(Trace {
+        tool: format!("{tool}[{}]", std::process::id()),
+        verbose,
+        file,
+    });
+    Ok(())
+}
+
+fn enabled(value: &OsStr) -> bool {
+    !value.is_empty() && value != "0"
+}
+
+/// Log a finished command: its exit code, or the error it could not be started with.
+pub(crate) fn command(cmd: &Command, result: Result<Option<i32>, &io::Error>, elapsed: Duration) {
+    let Some(trace) = TRACE.get() else {
+        return;
+    };
+    let line = format!("{}: {}", trace.tool, describe(cmd, result, elapsed));
+    if trace.verbose {
+        eprintln!("{line}");
+    }
+    if let Some(file) = &trace.file {
+        let mut file = file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = writeln!(file, "{line}");
+    }
+}
+
+/// `git status (cwd: /repo; env: GIT_DIR=/repo/.egit, -GIT_WORK_TREE; exit 0; 3.1ms)`
+fn describe(cmd: &Command, result: Result<Option<i32>, &io::Error>, elapsed: Duration) -> String {
+    let mut details = Vec::new();
+    if let Some(cwd) = cmd.get_current_dir() {
+        details.push(format!("cwd: {}", cwd.display()));
+    }
+    let env: Vec<String> = cmd
+        .get_envs()
+        .map(|(key, value)| match value {
+            Some(value) => format!("{}={}", key.to_string_lossy(), value.to_string_lossy()),
+            None => format!("-{}", key.to_string_lossy()),
+        })
+        .collect();
+    if !env.is_empty() {
+        details.push(format!("env: {}", env.join(", ")));
+    }
+    details.push(match result {
+        Ok(Some(code)) => format!("exit {code}"),
+        Ok(None) => "killed by a signal".to_string(),
+        Err(err) => format!("failed to start: {err}"),
+    });
+    details.push(format!("{:.1?}", elapsed));
+    format!("{} ({})", command_line(cmd), details.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_command() {
+        let mut cmd = Command::new("git");
+        cmd.args(["rev-parse", "HEAD"])
+            .current_dir("/repo")
+            .env("GIT_DIR", "/repo/.egit")
+            .env_remove("GIT_WORK_TREE");
+        assert_eq!(
+            describe(&cmd, Ok(Some(0)), Duration::from_micros(3100)),
+            "git rev-parse HEAD (cwd: /repo; env: GIT_DIR=/repo/.egit, -GIT_WORK_TREE; exit 0; 3.1ms)"
+        );
+
+        let err = io::Error::from(io::ErrorKind::NotFound);
+        assert!(describe(&Command::new("egit"), Err(&err), Duration::ZERO)
+            .starts_with("egit (failed to start: "));
+    }
+}
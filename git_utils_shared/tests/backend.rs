@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use git_test_fixture::TestRepo;
+use git_utils_shared::{backend::Subprocess, log::LogOptions};
+
+#[test]
+fn dash_prefixed_refs_are_revisions() {
+    let fixture = TestRepo::new("backend-dash-ref");
+    let first = fixture.commit_file("a.txt", "a", "Add a");
+    fixture.commit_file("b.txt", "b", "Add b");
+    fixture.git(&["update-ref", "refs/heads/--all", &first]);
+    let repo = fixture.repo().clone().with_backend(Arc::new(Subprocess));
+    let options = LogOptions::default();
+
+    let commits = repo.log(&["--all"], &options).unwrap();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0].hash, first);
+    assert_eq!(repo.count(&["--all"], &options).unwrap(), 1);
+    let patch_ids = repo.patch_ids(&["--all"], &options).unwrap();
+    assert_eq!(patch_ids.keys().collect::<Vec<_>>(), [&first]);
+}
+
+#[cfg(feature = "gitoxide")]
+#[test]
+fn gitoxide_matches_subprocess() {
+    use std::{fs, path::PathBuf};
+
+    use git_utils_shared::{
+        backend::{Backend, GitoxideBackend},
+        log::LogFilter,
+    };
+
+    let fixture = TestRepo::new("backend-gitoxide");
+    fixture.write("sub/a.txt", "a word\n");
+    fixture.write("sub/b.txt", "no match\n");
+    fixture.write("top.txt", "word\n");
+    fs::write(fixture.path().join("sub/c.bin"), b"word\0").unwrap();
+    fixture.commit("A");
+    fixture.branch("dev").commit_file("dev.txt", "d", "D");
+    fixture
+        .checkout("master")
+        .commit_file("master.txt", "m", "B");
+    fixture.git(&["branch", "--track", "follower", "master"]);
+    fixture.git(&["branch", "origin"]);
+    fixture.git(&["tag", "dev"]);
+    fixture.git(&["remote", "add", "origin", "https://example.com/repo.git"]);
+    fixture.git(&["update-ref", "refs/remotes/origin/master", "HEAD"]);
+    fixture.git(&[
+        "symbolic-ref",
+        "refs/remotes/origin/HEAD",
+        "refs/remotes/origin/master",
+    ]);
+    fixture.git(&["config", "branch.master.remote", "origin"]);
+    fixture.git(&["config", "branch.master.merge", "refs/heads/master"]);
+    fixture.write("sub/a.txt", "a changed word\n");
+    fs::remove_file(fixture.path().join("top.txt")).unwrap();
+
+    let repo = fixture.repo();
+    let sub = repo.at(fixture.path().join("sub"));
+    let gix = GitoxideBackend::default();
+    assert_eq!(
+        gix.rev_parse(repo, "HEAD").unwrap(),
+        Subprocess.rev_parse(repo, "HEAD").unwrap()
+    );
+    assert_eq!(
+        gix.merge_base(repo, "master", "heads/dev").unwrap(),
+        Subprocess.merge_base(repo, "master", "heads/dev").unwrap()
+    );
+    for revs in [
+        &["master", "heads/dev"][..],
+        &["master", "heads/dev", "follower"],
+    ] {
+        assert_eq!(
+            gix.merge_bases(repo, revs).unwrap(),
+            Subprocess.merge_bases(repo, revs).unwrap()
+        );
+    }
+    let all = LogFilter::default();
+    assert_eq!(
+        gix.log(repo, &["heads/dev"], &["master"], None, &all)
+            .unwrap(),
+        Subprocess
+            .log(repo, &["heads/dev"], &["master"], None, &all)
+            .unwrap()
+    );
+    for (revs, hidden) in [(["heads/dev"], ["master"]), (["master"], ["HEAD"])] {
+        assert_eq!(
+            gix.count(repo, &revs, &hidden, &all).unwrap(),
+            Subprocess.count(repo, &revs, &hidden, &all).unwrap()
+        );
+    }
+    for paths in [
+        vec![],
+        vec![PathBuf::from("a.txt")],
+        vec![PathBuf::from("..")],
+        vec![PathBuf::from("."), PathBuf::from("../top.txt")],
+        vec![PathBuf::from("missing")],
+    ] {
+        assert_eq!(
+            gix.ls_files(&sub, &paths).unwrap(),
+            Subprocess.ls_files(&sub, &paths).unwrap()
+        );
+        for pattern in ["word", "^no", "(w)\\1"] {
+            assert_eq!(
+                gix.grep(&sub, pattern, &paths).unwrap(),
+                Subprocess.grep(&sub, pattern, &paths).unwrap(),
+                "grep {pattern} {paths:?}"
+            );
+        }
+        assert_eq!(
+            gix.diff_name_only(&sub, &paths).unwrap(),
+            Subprocess.diff_name_only(&sub, &paths).unwrap(),
+            "diff {paths:?}"
+        );
+    }
+    let paths = [PathBuf::from(".")];
+    assert_eq!(
+        gix.grep(repo, "word", &paths).unwrap(),
+        Subprocess.grep(repo, "word", &paths).unwrap()
+    );
+    assert_eq!(
+        gix.diff_name_only(repo, &paths).unwrap(),
+        Subprocess.diff_name_only(repo, &paths).unwrap()
+    );
+    assert_eq!(
+        gix.local_branches(repo).unwrap(),
+        Subprocess.local_branches(repo).unwrap()
+    );
+    assert_eq!(
+        gix.ref_names(repo).unwrap(),
+        Subprocess.ref_names(repo).unwrap()
+    );
+}